@@ -0,0 +1,141 @@
+use crate::{
+    backup_json, game::to_player_name, load_json, now_timestamp, save_json, AppResult, PlayerId,
+};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Account {
+    pub id: PlayerId,
+    pub name: String,
+    // Seconds since the unix epoch.
+    pub created_at: u64,
+    pub last_seen: u64,
+}
+
+// Accounts of players authenticated with a public key, keyed by the key SHA256 fingerprint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountStore {
+    version: u32,
+    accounts: HashMap<String, Account>,
+    // Names of the connected guests, so that new accounts do not take them.
+    #[serde(skip)]
+    guest_names: HashSet<String>,
+    // Set when the accounts file could neither be loaded nor backed up,
+    // so that it is not overwritten.
+    #[serde(skip)]
+    save_disabled: bool,
+}
+
+impl Default for AccountStore {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            accounts: HashMap::new(),
+            guest_names: HashSet::new(),
+            save_disabled: false,
+        }
+    }
+}
+
+impl AccountStore {
+    const VERSION: u32 = 1;
+    const FILENAME: &'static str = "accounts.json";
+
+    pub fn load() -> AppResult<Self> {
+        let Some(store) = load_json::<Self>(Self::FILENAME)? else {
+            return Ok(Self::default());
+        };
+
+        if store.version != Self::VERSION {
+            return Err(anyhow!("Unsupported accounts version {}", store.version));
+        }
+
+        Ok(store)
+    }
+
+    pub fn save(&self) -> AppResult<()> {
+        if self.save_disabled {
+            return Ok(());
+        }
+        save_json(Self::FILENAME, self)
+    }
+
+    pub fn backup() -> AppResult<PathBuf> {
+        backup_json(Self::FILENAME)
+    }
+
+    pub fn disable_saving(&mut self) {
+        self.save_disabled = true;
+    }
+
+    // Saves the latest accounts, keeping the store locked only while it is cloned.
+    // Saves run one at a time, so that an older snapshot never replaces a newer one.
+    pub fn save_shared(store: &Mutex<Self>) -> AppResult<()> {
+        static SAVING: Mutex<()> = Mutex::new(());
+        let _saving = SAVING
+            .lock()
+            .map_err(|e| anyhow!("Account saving lock poisoned: {}", e))?;
+        let store = store
+            .lock()
+            .map_err(|e| anyhow!("Account store lock poisoned: {}", e))?
+            .clone();
+        store.save()
+    }
+
+    fn is_name_taken(&self, name: &str) -> bool {
+        self.guest_names.contains(name)
+            || self.accounts.values().any(|account| account.name == name)
+    }
+
+    fn new_name(&self, username: &str) -> String {
+        let rng = &mut rand::thread_rng();
+        let mut name = to_player_name(rng, username);
+        while self.is_name_taken(&name) {
+            name = to_player_name(rng, username);
+        }
+        name
+    }
+
+    // Guests get a name that no account or other guest uses, until they disconnect.
+    pub fn add_guest(&mut self, username: &str) -> String {
+        let name = self.new_name(username);
+        self.guest_names.insert(name.clone());
+        name
+    }
+
+    pub fn remove_guest(&mut self, name: &str) {
+        self.guest_names.remove(name);
+    }
+
+    pub fn account(&self, fingerprint: &str) -> Option<&Account> {
+        self.accounts.get(fingerprint)
+    }
+
+    // Returns the account for the given fingerprint, creating it with a new
+    // unique hero name based on the username if it does not exist yet.
+    pub fn login(&mut self, fingerprint: &str, username: &str) -> Account {
+        let now = now_timestamp();
+
+        if let Some(account) = self.accounts.get_mut(fingerprint) {
+            account.last_seen = now;
+            return account.clone();
+        }
+
+        let account = Account {
+            id: PlayerId::new_v4(),
+            name: self.new_name(username),
+            created_at: now,
+            last_seen: now,
+        };
+        self.accounts
+            .insert(fingerprint.to_string(), account.clone());
+
+        account
+    }
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use strum_macros::{Display, EnumIter};
+
+#[derive(Debug, Clone, Copy, Display, PartialEq, Eq, Hash, EnumIter, Serialize, Deserialize)]
+pub enum Achievement {
+    Escapist,
+    Purist,
+    Marathoner,
+    Speedrunner,
+    Elephant,
+}
+
+impl Achievement {
+    pub const TOAST_DURATION: Duration = Duration::from_secs(4);
+    pub const PURIST_MAZE_ID: usize = 4;
+    pub const MARATHONER_CHASE_DURATION: Duration = Duration::from_secs(30);
+    pub const SPEEDRUNNER_DURATION: Duration = Duration::from_secs(5 * 60);
+    pub const ELEPHANT_MEMORY_POWER_UPS: usize = 3;
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Escapist => "Exit the labyrinth",
+            Self::Purist => "Clear room 5 without power ups",
+            Self::Marathoner => "Survive a 30 seconds chase",
+            Self::Speedrunner => "Win in under 5 minutes",
+            Self::Elephant => "Collect 3 Memory power ups in a run",
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use super::{Direction, IntoDirection, Position};
+use crate::{AppResult, PlayerId};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use strum_macros::Display;
+
+#[derive(Debug, Default, Clone, Copy, Display, PartialEq)]
+pub enum ChatChannel {
+    // Only heros in the same room read room messages.
+    #[default]
+    Room,
+    Global,
+}
+
+impl ChatChannel {
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Room => Self::Global,
+            Self::Global => Self::Room,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatMessage {
+    pub sender: String,
+    pub channel: ChatChannel,
+    pub maze_id: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PingKind {
+    MinotaurHere,
+    ExitThisWay,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ping {
+    pub kind: PingKind,
+    pub maze_id: usize,
+    pub position: Position,
+    // Where the hero was looking, exit pings point that way.
+    pub direction: Direction,
+    instant: Instant,
+}
+
+impl Ping {
+    pub fn glyph(&self) -> char {
+        match self.kind {
+            PingKind::MinotaurHere => '!',
+            PingKind::ExitThisWay => self.direction.arrow(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Chat {
+    messages: Vec<ChatMessage>,
+    pings: Vec<Ping>,
+    // When each player last talked, used for flood protection.
+    recent: HashMap<PlayerId, Vec<Instant>>,
+}
+
+impl Chat {
+    pub const MAX_MESSAGE_LENGTH: usize = 80;
+    const MAX_MESSAGES: usize = 50;
+    const FLOOD_WINDOW: Duration = Duration::from_secs(10);
+    const MAX_MESSAGES_PER_WINDOW: usize = 5;
+    const PING_DURATION: Duration = Duration::from_secs(8);
+    // Pings are only shown to heros within this distance.
+    pub const PING_RADIUS: f64 = 24.0;
+
+    fn check_flood(&mut self, player_id: PlayerId) -> AppResult<()> {
+        let recent = self.recent.entry(player_id).or_default();
+        recent.retain(|instant| instant.elapsed() < Self::FLOOD_WINDOW);
+        if recent.len() >= Self::MAX_MESSAGES_PER_WINDOW {
+            return Err(anyhow!("Slow down! Too many messages"));
+        }
+
+        recent.push(Instant::now());
+        Ok(())
+    }
+
+    pub fn send(
+        &mut self,
+        player_id: PlayerId,
+        sender: &str,
+        maze_id: usize,
+        channel: ChatChannel,
+        text: &str,
+    ) -> AppResult<()> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(());
+        }
+        if text.chars().count() > Self::MAX_MESSAGE_LENGTH {
+            return Err(anyhow!(
+                "Messages are limited to {} characters",
+                Self::MAX_MESSAGE_LENGTH
+            ));
+        }
+        self.check_flood(player_id)?;
+
+        self.messages.push(ChatMessage {
+            sender: sender.to_string(),
+            channel,
+            maze_id,
+            text: text.chars().filter(|c| !c.is_control()).collect(),
+        });
+        if self.messages.len() > Self::MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+
+        Ok(())
+    }
+
+    // Pings share the flood protection of messages, and are dropped when flooding.
+    pub fn ping(
+        &mut self,
+        player_id: PlayerId,
+        kind: PingKind,
+        maze_id: usize,
+        position: Position,
+        direction: Direction,
+    ) {
+        if self.check_flood(player_id).is_err() {
+            return;
+        }
+
+        self.pings
+            .retain(|ping| ping.maze_id != maze_id || ping.position != position);
+        self.pings.push(Ping {
+            kind,
+            maze_id,
+            position,
+            direction,
+            instant: Instant::now(),
+        });
+    }
+
+    // Global messages and the room messages of the given room, oldest first.
+    pub fn messages(&self, maze_id: usize) -> impl Iterator<Item = &ChatMessage> {
+        self.messages.iter().filter(move |message| {
+            message.channel == ChatChannel::Global || message.maze_id == maze_id
+        })
+    }
+
+    pub fn pings_near(&self, maze_id: usize, position: Position) -> impl Iterator<Item = &Ping> {
+        self.pings.iter().filter(move |ping| {
+            ping.maze_id == maze_id && ping.position.distance(position) <= Self::PING_RADIUS
+        })
+    }
+
+    pub fn update(&mut self) {
+        self.pings
+            .retain(|ping| ping.instant.elapsed() < Self::PING_DURATION);
+        self.recent.retain(|_, recent| {
+            recent
+                .last()
+                .is_some_and(|instant| instant.elapsed() < Self::FLOOD_WINDOW)
+        });
+    }
+
+    pub fn remove_player(&mut self, player_id: &PlayerId) {
+        self.recent.remove(player_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Chat, ChatChannel};
+    use crate::PlayerId;
+    use itertools::Itertools;
+
+    #[test]
+    fn test_chat() {
+        let mut chat = Chat::default();
+        let player_id = PlayerId::new_v4();
+
+        chat.send(player_id, "first", 0, ChatChannel::Room, "hello room")
+            .expect("Message should be sent");
+        chat.send(player_id, "first", 0, ChatChannel::Global, "hello all")
+            .expect("Message should be sent");
+        assert_eq!(chat.messages(0).count(), 2);
+        assert_eq!(
+            chat.messages(1)
+                .map(|message| message.text.as_str())
+                .collect_vec(),
+            vec!["hello all"]
+        );
+
+        let other_id = PlayerId::new_v4();
+        assert!(chat
+            .send(other_id, "second", 1, ChatChannel::Global, &"a".repeat(100))
+            .is_err());
+        for _ in 0..3 {
+            chat.send(player_id, "first", 0, ChatChannel::Room, "spam")
+                .expect("Message should be sent");
+        }
+        assert!(chat
+            .send(player_id, "first", 0, ChatChannel::Room, "spam")
+            .is_err());
+    }
+}
@@ -0,0 +1,207 @@
+use super::MAX_MAZE_ID;
+use crate::AppResult;
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::{path::Path, time::Duration};
+
+// A value growing every few rooms, up to a maximum.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoomScaling {
+    pub base: f64,
+    pub step: f64,
+    pub rooms_per_step: usize,
+    pub max: f64,
+}
+
+impl RoomScaling {
+    const fn new(base: f64, step: f64, rooms_per_step: usize, max: f64) -> Self {
+        Self {
+            base,
+            step,
+            rooms_per_step,
+            max,
+        }
+    }
+
+    pub fn value(&self, maze_id: usize) -> f64 {
+        (self.base + self.step * (maze_id / self.rooms_per_step) as f64).min(self.max)
+    }
+
+    fn validate(&self, name: &str) -> AppResult<()> {
+        if self.rooms_per_step == 0 {
+            return Err(anyhow!("{}.rooms_per_step should be at least 1", name));
+        }
+        if self.base < 0.0 || self.max < self.base {
+            return Err(anyhow!("{} should have 0 <= base <= max", name));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HeroConfig {
+    pub initial_speed: u64,
+    pub max_speed: u64,
+    pub initial_vision: usize,
+    pub max_vision: usize,
+    pub initial_memory: u64,
+    pub initial_flares: usize,
+    // Shortest time between two moves, reached at full speed.
+    pub min_move_interval_ms: u64,
+}
+
+impl Default for HeroConfig {
+    fn default() -> Self {
+        Self {
+            initial_speed: 4,
+            max_speed: 8,
+            initial_vision: 1,
+            max_vision: 8,
+            initial_memory: 0,
+            initial_flares: 2,
+            min_move_interval_ms: 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MinotaurConfig {
+    // Number of minotaurs spawned in each room.
+    pub count: RoomScaling,
+    pub speed: RoomScaling,
+    pub vision: RoomScaling,
+    pub aggression: RoomScaling,
+}
+
+impl Default for MinotaurConfig {
+    fn default() -> Self {
+        Self {
+            count: RoomScaling::new(0.0, 1.0, 1, 100.0),
+            speed: RoomScaling::new(0.0, 1.0, 3, 6.0),
+            vision: RoomScaling::new(4.0, 1.0, 3, 7.0),
+            aggression: RoomScaling::new(0.5, 0.1, 2, 1.0),
+        }
+    }
+}
+
+// Rules of the game, read from a TOML file so that they can be tuned without recompiling.
+// Missing values keep their default.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameConfig {
+    pub number_of_rooms: usize,
+    pub respawn_interval_ms: u64,
+    // Heros who do not move for this long are disconnected, as are idle SSH sessions.
+    pub inactivity_timeout_secs: u64,
+    // Heros of registered players whose connection dropped wait this long for them to reconnect.
+    pub reconnect_grace_secs: u64,
+    // Dead heros of co-op games wait this long for a teammate to revive them.
+    pub revive_grace_secs: u64,
+    pub hero: HeroConfig,
+    pub minotaurs: MinotaurConfig,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            number_of_rooms: MAX_MAZE_ID,
+            respawn_interval_ms: 1500,
+            inactivity_timeout_secs: 60,
+            reconnect_grace_secs: 60,
+            revive_grace_secs: 20,
+            hero: HeroConfig::default(),
+            minotaurs: MinotaurConfig::default(),
+        }
+    }
+}
+
+impl GameConfig {
+    pub fn load(path: &Path) -> AppResult<Self> {
+        let config: Self = toml::from_str(&std::fs::read_to_string(path)?)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> AppResult<()> {
+        if self.number_of_rooms == 0 {
+            return Err(anyhow!("number_of_rooms should be at least 1"));
+        }
+        if self.hero.initial_speed > self.hero.max_speed {
+            return Err(anyhow!(
+                "hero.initial_speed should not exceed hero.max_speed"
+            ));
+        }
+        if self.hero.initial_vision == 0 || self.hero.initial_vision > self.hero.max_vision {
+            return Err(anyhow!(
+                "hero.initial_vision should be between 1 and hero.max_vision"
+            ));
+        }
+
+        self.minotaurs.count.validate("minotaurs.count")?;
+        self.minotaurs.speed.validate("minotaurs.speed")?;
+        self.minotaurs.vision.validate("minotaurs.vision")?;
+        self.minotaurs.aggression.validate("minotaurs.aggression")?;
+        if self.minotaurs.aggression.max > 1.0 {
+            return Err(anyhow!("minotaurs.aggression.max should not exceed 1"));
+        }
+
+        Ok(())
+    }
+
+    pub fn respawn_interval(&self) -> Duration {
+        Duration::from_millis(self.respawn_interval_ms)
+    }
+
+    pub fn inactivity_timeout(&self) -> Duration {
+        Duration::from_secs(self.inactivity_timeout_secs)
+    }
+
+    pub fn reconnect_grace(&self) -> Duration {
+        Duration::from_secs(self.reconnect_grace_secs)
+    }
+
+    pub fn revive_grace(&self) -> Duration {
+        Duration::from_secs(self.revive_grace_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GameConfig;
+    use crate::PlayerId;
+
+    #[test]
+    fn test_game_config() {
+        let path = std::env::temp_dir().join(format!("asterion-{}.toml", PlayerId::new_v4()));
+        std::fs::write(
+            &path,
+            r#"
+number_of_rooms = 3
+
+[hero]
+initial_flares = 5
+
+[minotaurs.count]
+base = 2
+step = 0
+rooms_per_step = 1
+max = 2
+"#,
+        )
+        .expect("Config should be written");
+        let config = GameConfig::load(&path).expect("Config should load");
+        std::fs::remove_file(&path).expect("Config should be removed");
+
+        assert_eq!(config.number_of_rooms, 3);
+        assert_eq!(config.hero.initial_flares, 5);
+        assert_eq!(config.hero.max_speed, GameConfig::default().hero.max_speed);
+        assert_eq!(config.minotaurs.count.value(2), 2.0);
+
+        std::fs::write(&path, "number_of_rooms = 0").expect("Config should be written");
+        assert!(GameConfig::load(&path).is_err());
+        std::fs::remove_file(&path).expect("Config should be removed");
+    }
+}
@@ -1,6 +1,7 @@
+use serde::{Deserialize, Serialize};
 use strum_macros::{Display, EnumIter};
 
-#[derive(Debug, Copy, Clone, Display, PartialEq, Eq, Hash, EnumIter)]
+#[derive(Debug, Copy, Clone, Display, PartialEq, Eq, Hash, EnumIter, Serialize, Deserialize)]
 pub enum Direction {
     North,
     East,
@@ -64,4 +65,17 @@ impl Direction {
             Self::NorthWest => Self::West,
         }
     }
+
+    pub fn arrow(&self) -> char {
+        match self {
+            Self::North => '↑',
+            Self::East => '→',
+            Self::South => '↓',
+            Self::West => '←',
+            Self::NorthEast => '↗',
+            Self::SouthEast => '↘',
+            Self::SouthWest => '↙',
+            Self::NorthWest => '↖',
+        }
+    }
 }
@@ -0,0 +1,29 @@
+use image::Rgba;
+use strum_macros::{Display, EnumIter};
+
+#[derive(Debug, Clone, Copy, Display, PartialEq, Eq, Hash, EnumIter)]
+pub enum KeyColor {
+    Copper,
+    Silver,
+    Violet,
+}
+
+impl KeyColor {
+    pub fn rgba(&self) -> Rgba<u8> {
+        match self {
+            Self::Copper => Rgba([184, 115, 51, 255]),
+            Self::Silver => Rgba([192, 192, 192, 255]),
+            Self::Violet => Rgba([148, 87, 235, 255]),
+        }
+    }
+
+    // Doors are drawn with a darker shade of the color of the key that opens them.
+    pub fn door_rgba(&self) -> Rgba<u8> {
+        let [r, g, b, a] = self.rgba().0;
+        Rgba([r / 2, g / 2, b / 2, a])
+    }
+
+    pub fn glyph() -> char {
+        '⚷'
+    }
+}
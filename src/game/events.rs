@@ -0,0 +1,110 @@
+use super::{Achievement, Position, PowerUp, Trap};
+use crate::PlayerId;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    HeroJoined {
+        hero_id: PlayerId,
+    },
+    HeroLeft {
+        hero_id: PlayerId,
+    },
+    RunStarted {
+        hero_id: PlayerId,
+    },
+    HeroMoved {
+        hero_id: PlayerId,
+        maze_id: usize,
+        position: Position,
+    },
+    HeroDied {
+        hero_id: PlayerId,
+        maze_id: usize,
+        // Name of the minotaur that caught the hero, if any.
+        killer: Option<String>,
+    },
+    HeroRevived {
+        hero_id: PlayerId,
+        by: PlayerId,
+    },
+    TrapTriggered {
+        hero_id: PlayerId,
+        maze_id: usize,
+        trap: Trap,
+    },
+    PowerUpCollected {
+        hero_id: PlayerId,
+        maze_id: usize,
+        power_up: PowerUp,
+    },
+    PowerUpGiven {
+        hero_id: PlayerId,
+        to: PlayerId,
+        power_up: PowerUp,
+    },
+    RoomEntered {
+        hero_id: PlayerId,
+        from: usize,
+        to: usize,
+    },
+    RoomCleared {
+        hero_id: PlayerId,
+        maze_id: usize,
+        duration: Duration,
+    },
+    ChaseEnded {
+        hero_id: PlayerId,
+        duration: Duration,
+    },
+    Victory {
+        hero_id: PlayerId,
+        duration: Duration,
+    },
+    NewRecord {
+        hero_id: PlayerId,
+        maze_id: usize,
+        duration: Duration,
+    },
+    AchievementUnlocked {
+        hero_id: PlayerId,
+        achievement: Achievement,
+    },
+}
+
+// Events are queued for the game to process at the end of each update or
+// command, and broadcast to external subscribers.
+#[derive(Debug)]
+pub struct EventBus {
+    pending: Vec<GameEvent>,
+    sender: broadcast::Sender<GameEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(Self::CHANNEL_CAPACITY);
+        Self {
+            pending: vec![],
+            sender,
+        }
+    }
+}
+
+impl EventBus {
+    const CHANNEL_CAPACITY: usize = 1024;
+
+    pub fn emit(&mut self, event: GameEvent) {
+        // Sending only fails when there are no subscribers.
+        let _ = self.sender.send(event.clone());
+        self.pending.push(event);
+    }
+
+    pub fn drain(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.pending)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<GameEvent> {
+        self.sender.subscribe()
+    }
+}
@@ -1,36 +1,142 @@
 use super::{
     entity::Entity,
+    events::{EventBus, GameEvent},
+    ghost::GhostRun,
     hero::{HeroCommand, HeroState},
     minotaur::Minotaur,
-    utils::{random_minotaur_name, to_player_name},
-    AlarmLevel, GameColors, Hero, IntoDirection, Maze,
+    utils::random_minotaur_name,
+    Achievement, AlarmLevel, Chat, ChatChannel, ChatMessage, Direction, GameColors, GameConfig,
+    Ghost, GhostStore, HallOfFame, Hero, HeroRecord, HeroSnapshot, HeroStats, IntoDirection,
+    LightSource, LightSourceKind, Maze, MinotaurRecord, MinotaurSnapshot, Position, PowerUp, Race,
+    RaceResult, Replay, ReplayChunk, ReplayHeroState, StatsStore, Trap,
 };
-use crate::{ui::utils::RataColor, AppResult, PlayerId};
+use crate::{load_store, now_timestamp, ui::utils::RataColor, AppResult, PlayerId};
 use anyhow::anyhow;
 use image::{Rgba, RgbaImage};
 use itertools::Itertools;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{hash_map::Entry, HashMap, HashSet},
     time::{Duration, Instant},
 };
 
+// Default number of rooms. Rooms also get darker over this many rooms.
 pub const MAX_MAZE_ID: usize = 10;
 
+// The public game is shared by everyone, private ones are joined with an invite code.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GameInstance {
+    Public,
+    Private { invite_code: String },
+}
+
+// Optional interactions between heros, set when creating a private game.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct HeroInteractions {
+    // Heros cannot walk through each other.
+    pub blocking: bool,
+    // Heros can push the hero in front of them one tile.
+    pub shoving: bool,
+    // Heros can hand the power up of the room to an adjacent hero.
+    pub trading: bool,
+}
+
+impl HeroInteractions {
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            (self.blocking, "blocking"),
+            (self.shoving, "shoving"),
+            (self.trading, "trading"),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, name)| *name)
+        .collect_vec()
+    }
+}
+
 pub struct Game {
-    mazes: [Maze; MAX_MAZE_ID],
-    taken_names: HashSet<String>,
+    instance: GameInstance,
+    config: GameConfig,
+    mazes: Vec<Maze>,
     heros: HashMap<PlayerId, Hero>,
-    hero_rooms: [Vec<PlayerId>; MAX_MAZE_ID],
+    hero_rooms: Vec<Vec<PlayerId>>,
     top_heros_map: HashMap<PlayerId, (String, usize, Duration)>,
     top_heros: Vec<(PlayerId, String, usize, Duration)>,
     minotaurs: HashMap<PlayerId, Minotaur>,
-    minotaur_rooms: [Vec<PlayerId>; MAX_MAZE_ID],
+    minotaur_rooms: Vec<Vec<PlayerId>>,
     top_minotaurs_map: HashMap<PlayerId, (String, usize, usize)>,
     top_minotaurs: Vec<(PlayerId, String, usize, usize)>,
+    // Unix timestamps of when each hero and minotaur record was last set.
+    record_timestamps: HashMap<PlayerId, u64>,
+    stats: HashMap<PlayerId, HeroStats>,
+    // Guests stats are only kept while they are connected.
+    guests: HashSet<PlayerId>,
+    last_update: Instant,
+    achievement_toasts: HashMap<PlayerId, Vec<(Achievement, Instant)>>,
+    // When each hero started being chased without interruption.
+    chase_instants: HashMap<PlayerId, Instant>,
+    // Heros whose connection dropped, with when it happened.
+    // They are left alone by minotaurs until their player reconnects.
+    detached: HashMap<PlayerId, Instant>,
+    events: EventBus,
+    kill_feed: Vec<(String, Instant)>,
+    chat: Chat,
+    tick: u64,
+    // Replays of registered heros, recorded while they run.
+    replays: HashMap<PlayerId, Replay>,
+    replay_chunks: Vec<ReplayChunk>,
+    // Best ghost of each hero, keyed by maze seed.
+    ghosts: HashMap<PlayerId, HashMap<u64, Ghost>>,
+    ghost_runs: HashMap<PlayerId, GhostRun>,
+    race: Option<Race>,
+    // In co-op games all heros are teammates.
+    is_coop: bool,
+    interactions: HeroInteractions,
+    // Deepest room reached by every teammate.
+    team_best_depth: usize,
+    // Stores that could neither be loaded nor backed up are never saved,
+    // so that they are not overwritten.
+    save_hall_of_fame: bool,
+    save_stats: bool,
+    save_ghosts: bool,
+}
+
+// Stores persisted by the public game. Stores that could neither be loaded nor
+// backed up are never saved, so that they are not overwritten. The default
+// stores are empty and never saved.
+#[derive(Debug, Default)]
+pub struct GameStores {
+    pub hall_of_fame: HallOfFame,
+    pub stats: StatsStore,
+    pub ghosts: GhostStore,
+    save_hall_of_fame: bool,
+    save_stats: bool,
+    save_ghosts: bool,
+}
+
+impl GameStores {
+    pub fn load() -> Self {
+        let (hall_of_fame, save_hall_of_fame) =
+            load_store("hall of fame", HallOfFame::load, HallOfFame::backup);
+        let (stats, save_stats) = load_store("stats", StatsStore::load, StatsStore::backup);
+        let (ghosts, save_ghosts) = load_store("ghosts", GhostStore::load, GhostStore::backup);
+
+        Self {
+            hall_of_fame,
+            stats,
+            ghosts,
+            save_hall_of_fame,
+            save_stats,
+            save_ghosts,
+        }
+    }
 }
 
 impl Game {
-    const RESPAWN_INTERVAL: Duration = Duration::from_millis(1500);
+    const KILL_FEED_DURATION: Duration = Duration::from_secs(8);
+    const MAX_KILL_FEED_LENGTH: usize = 4;
 
     fn should_update_hero_record(&self, hero_id: PlayerId) -> bool {
         let hero = if let Some(hero) = self.get_hero(&hero_id) {
@@ -47,7 +153,7 @@ impl Game {
             };
 
         if let Some(duration) = hero.has_won() {
-            if record_maze_id < MAX_MAZE_ID {
+            if record_maze_id < self.number_of_rooms() {
                 return true;
             }
             return record_timer > duration;
@@ -72,7 +178,7 @@ impl Game {
             };
 
             let record = if let Some(duration) = hero.has_won() {
-                (hero.name().to_string(), MAX_MAZE_ID, duration)
+                (hero.name().to_string(), self.number_of_rooms(), duration)
             } else {
                 (
                     hero.name().to_string(),
@@ -80,8 +186,14 @@ impl Game {
                     hero.elapsed_duration_from_start(),
                 )
             };
+            self.events.emit(GameEvent::NewRecord {
+                hero_id,
+                maze_id: record.1,
+                duration: record.2,
+            });
             self.top_heros_map.insert(hero_id, record);
-            self.update_top_heros();
+            self.record_timestamps.insert(hero_id, now_timestamp());
+            self.prune_records();
         }
     }
 
@@ -104,23 +216,16 @@ impl Game {
 
     fn update_top_minotaurs(&mut self) {
         self.top_minotaurs = self
-            .minotaurs
-            .values()
+            .top_minotaurs_map
+            .iter()
+            .map(|(&id, (name, maze_id, kills))| (id, name.clone(), *maze_id, *kills))
             .sorted_by(|a, b| {
-                if b.kills == a.kills {
-                    b.maze_id().cmp(&a.maze_id())
+                if b.3 == a.3 {
+                    b.2.cmp(&a.2)
                 } else {
-                    b.kills.cmp(&a.kills)
+                    b.3.cmp(&a.3)
                 }
             })
-            .map(|minotaur| {
-                (
-                    minotaur.id(),
-                    minotaur.name().to_string(),
-                    minotaur.maze_id(),
-                    minotaur.kills,
-                )
-            })
             .collect_vec();
     }
 
@@ -132,50 +237,1075 @@ impl Game {
         Duration::from_millis(50)
     }
 
-    pub fn new() -> Self {
-        let mut mazes = (0..MAX_MAZE_ID)
-            .map(|maze_id| Maze::random(maze_id))
-            .collect_array()
-            .expect("Should create maze array");
+    pub fn save_time_step() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn with_mazes(mazes: Vec<Maze>, config: GameConfig) -> Self {
+        Self {
+            instance: GameInstance::Public,
+            config,
+            hero_rooms: vec![vec![]; mazes.len()],
+            minotaur_rooms: vec![vec![]; mazes.len()],
+            mazes,
+            heros: HashMap::new(),
+            top_heros_map: HashMap::new(),
+            top_heros: vec![],
+            minotaurs: HashMap::new(),
+            top_minotaurs_map: HashMap::new(),
+            top_minotaurs: vec![],
+            record_timestamps: HashMap::new(),
+            stats: HashMap::new(),
+            guests: HashSet::new(),
+            last_update: Instant::now(),
+            achievement_toasts: HashMap::new(),
+            chase_instants: HashMap::new(),
+            detached: HashMap::new(),
+            events: EventBus::default(),
+            kill_feed: vec![],
+            chat: Chat::default(),
+            tick: 0,
+            replays: HashMap::new(),
+            replay_chunks: vec![],
+            ghosts: HashMap::new(),
+            ghost_runs: HashMap::new(),
+            race: None,
+            is_coop: false,
+            interactions: HeroInteractions::default(),
+            team_best_depth: 0,
+            save_hall_of_fame: true,
+            save_stats: true,
+            save_ghosts: true,
+        }
+    }
+
+    pub fn new(config: GameConfig, stores: GameStores) -> Self {
+        Self::generate(None, GameInstance::Public, config, stores)
+    }
+
+    // Rooms generated from the same seed are the same across restarts,
+    // so that heros can race against their ghosts.
+    pub fn with_seed(seed: u64, config: GameConfig, stores: GameStores) -> Self {
+        Self::generate(Some(seed), GameInstance::Public, config, stores)
+    }
+
+    // Private games start with an empty hall of fame and are never persisted.
+    pub fn new_private(invite_code: String, config: GameConfig) -> Self {
+        Self::generate(
+            None,
+            GameInstance::Private { invite_code },
+            config,
+            GameStores::default(),
+        )
+    }
+
+    pub fn new_race(invite_code: String, config: GameConfig) -> Self {
+        Self {
+            race: Some(Race::Gathering),
+            ..Self::new_private(invite_code, config)
+        }
+    }
+
+    pub fn new_coop(invite_code: String, config: GameConfig) -> Self {
+        Self {
+            is_coop: true,
+            ..Self::new_private(invite_code, config)
+        }
+    }
+
+    fn generate_rooms(
+        seed: Option<u64>,
+        config: &GameConfig,
+    ) -> (Vec<Maze>, HashMap<PlayerId, Minotaur>, Vec<Vec<PlayerId>>) {
+        let mut seeds_rng = seed.map(ChaCha8Rng::seed_from_u64);
+        let mut mazes = (0..config.number_of_rooms)
+            .map(|maze_id| match seeds_rng.as_mut() {
+                Some(rng) => Maze::from_seed(maze_id, rng.gen()),
+                None => Maze::random(maze_id),
+            })
+            .collect_vec();
 
         let mut minotaurs = HashMap::new();
-        let mut minotaur_rooms = [const { Vec::new() }; MAX_MAZE_ID];
+        let mut minotaur_rooms = vec![vec![]; mazes.len()];
+
+        for maze in mazes.iter_mut() {
+            let mut maze_minotaurs = vec![];
+            for index in 0..config.minotaurs.count.value(maze.id) as usize {
+                let name = format!("{}#{}{}", random_minotaur_name(), maze.id, index);
+                let minotaur = maze.spawn_minotaur(name, &config.minotaurs);
+                maze_minotaurs.push(minotaur.id());
+                minotaurs.insert(minotaur.id(), minotaur);
+            }
+            minotaur_rooms[maze.id] = maze_minotaurs;
+        }
+
+        (mazes, minotaurs, minotaur_rooms)
+    }
+
+    fn generate(
+        seed: Option<u64>,
+        instance: GameInstance,
+        config: GameConfig,
+        stores: GameStores,
+    ) -> Self {
+        let (mazes, minotaurs, minotaur_rooms) = Self::generate_rooms(seed, &config);
+
+        let mut game = Self {
+            instance,
+            minotaurs,
+            minotaur_rooms,
+            stats: stores.stats.stats,
+            ghosts: stores.ghosts.ghosts,
+            save_hall_of_fame: stores.save_hall_of_fame,
+            save_stats: stores.save_stats,
+            save_ghosts: stores.save_ghosts,
+            ..Self::with_mazes(mazes, config)
+        };
+        game.prune_ghosts();
+
+        let hall_of_fame = stores.hall_of_fame;
+        for record in hall_of_fame.heros {
+            game.top_heros_map
+                .insert(record.id, (record.name, record.maze_id, record.duration));
+            game.record_timestamps.insert(record.id, record.timestamp);
+        }
+
+        for record in hall_of_fame.minotaurs {
+            game.top_minotaurs_map
+                .insert(record.id, (record.name, record.maze_id, record.kills));
+            game.record_timestamps.insert(record.id, record.timestamp);
+        }
+
+        for minotaur in game.minotaurs.values() {
+            game.top_minotaurs_map.insert(
+                minotaur.id(),
+                (minotaur.name().to_string(), minotaur.maze_id(), 0),
+            );
+        }
+
+        game.update_top_heros();
+        game.update_top_minotaurs();
+
+        game
+    }
+
+    // A game holding only the replay hero, whose state is set with set_replay_state.
+    pub fn from_replay(replay: &Replay) -> AppResult<Self> {
+        let mazes = replay
+            .header
+            .mazes
+            .iter()
+            .map(Maze::from_descriptor)
+            .collect_vec();
+        if mazes.is_empty() {
+            return Err(anyhow!("Replay should have at least one maze"));
+        }
+
+        let config = GameConfig {
+            number_of_rooms: mazes.len(),
+            ..replay.header.config
+        };
+        let mut game = Self::with_mazes(mazes, config);
+        let hero_id = replay.header.hero_id;
+        let mut hero = Hero::new(
+            hero_id,
+            replay.header.hero_name.clone(),
+            game.mazes[0].hero_starting_position(),
+            config.hero,
+        );
+        // The game is drawn before the first frame is applied.
+        let visible_positions = game.mazes[0].get_and_cache_visible_positions(
+            hero.position(),
+            hero.direction(),
+            hero.view(),
+        );
+        hero.update_past_visible_positions(visible_positions);
+        game.hero_rooms[0].push(hero_id);
+        game.heros.insert(hero_id, hero);
+
+        Ok(game)
+    }
+
+    // Sets the state of the replay hero and of the minotaurs in its maze.
+    // Elapsed is the duration of the run so far.
+    pub fn set_replay_state(
+        &mut self,
+        hero_id: PlayerId,
+        snapshot: &HeroSnapshot,
+        elapsed: Duration,
+        minotaurs: &[MinotaurSnapshot],
+    ) {
+        let Some(hero) = self.heros.get_mut(&hero_id) else {
+            return;
+        };
+
+        hero.state = match snapshot.state {
+            ReplayHeroState::WaitingToStart => HeroState::WaitingToStart,
+            ReplayHeroState::InMaze => HeroState::InMaze {
+                instant: Instant::now() - elapsed,
+            },
+            ReplayHeroState::Dead { duration } => HeroState::Dead {
+                duration,
+                instant: Instant::now(),
+            },
+            ReplayHeroState::Victory { duration } => HeroState::Victory {
+                duration,
+                instant: Instant::now(),
+            },
+        };
+
+        if hero.maze_id() != snapshot.maze_id {
+            self.hero_rooms[hero.maze_id()].retain(|id| *id != hero_id);
+            self.hero_rooms[snapshot.maze_id].push(hero_id);
+            hero.set_maze_id(snapshot.maze_id);
+        }
+        hero.set_position(snapshot.position);
+        hero.set_direction(snapshot.direction);
+        hero.set_vision(snapshot.vision);
+
+        let maze = &mut self.mazes[snapshot.maze_id];
+        hero.update_past_visible_positions(maze.get_and_cache_visible_positions(
+            hero.position(),
+            hero.direction(),
+            hero.view(),
+        ));
+
+        self.minotaurs.clear();
+        for room in self.minotaur_rooms.iter_mut() {
+            room.clear();
+        }
+        for snapshot_minotaur in minotaurs {
+            let minotaur = Minotaur::from_snapshot(snapshot_minotaur, snapshot.maze_id, hero_id);
+            self.minotaur_rooms[snapshot.maze_id].push(minotaur.id());
+            self.minotaurs.insert(minotaur.id(), minotaur);
+        }
+    }
+
+    // Snapshot of the records to be persisted, if the hall of fame can be saved.
+    pub fn hall_of_fame(&self) -> Option<HallOfFame> {
+        self.save_hall_of_fame.then(|| self.records())
+    }
+
+    fn records(&self) -> HallOfFame {
+        let now = now_timestamp();
+        let timestamp = |id: &PlayerId| self.record_timestamps.get(id).copied().unwrap_or(now);
+
+        let heros = self
+            .top_heros_map
+            .iter()
+            .map(|(&id, (name, maze_id, duration))| HeroRecord {
+                id,
+                name: name.clone(),
+                maze_id: *maze_id,
+                duration: *duration,
+                timestamp: timestamp(&id),
+            })
+            .collect_vec();
+
+        // Minotaurs without kills are not worth remembering.
+        let minotaurs = self
+            .top_minotaurs_map
+            .iter()
+            .filter(|(_, (_, _, kills))| *kills > 0)
+            .map(|(&id, (name, maze_id, kills))| MinotaurRecord {
+                id,
+                name: name.clone(),
+                maze_id: *maze_id,
+                kills: *kills,
+                timestamp: timestamp(&id),
+            })
+            .collect_vec();
+
+        HallOfFame::new(heros, minotaurs)
+    }
+
+    // Records dropped by the retention policy are forgotten as soon as new ones are set.
+    fn prune_records(&mut self) {
+        let record_ids = self.records().record_ids();
+
+        self.top_heros_map.retain(|id, _| record_ids.contains(id));
+        self.top_minotaurs_map.retain(|id, (_, _, kills)| {
+            record_ids.contains(id) || (*kills == 0 && self.minotaurs.contains_key(id))
+        });
+        self.record_timestamps
+            .retain(|id, _| record_ids.contains(id));
+        self.update_top_heros();
+        self.update_top_minotaurs();
+    }
+
+    pub fn ghost_store(&self) -> Option<GhostStore> {
+        self.save_ghosts.then(|| {
+            GhostStore::new(
+                self.ghosts
+                    .iter()
+                    .filter(|(id, _)| !self.guests.contains(id))
+                    .map(|(&id, ghosts)| (id, ghosts.clone()))
+                    .collect(),
+            )
+        })
+    }
+
+    // Ghosts of rooms that are not played anymore can never be raced against.
+    fn prune_ghosts(&mut self) {
+        let seeds = self
+            .mazes
+            .iter()
+            .map(|maze| maze.random_seed())
+            .collect::<HashSet<_>>();
+        for hero_ghosts in self.ghosts.values_mut() {
+            hero_ghosts.retain(|seed, _| seeds.contains(seed));
+        }
+        self.ghosts.retain(|_, hero_ghosts| !hero_ghosts.is_empty());
+    }
+
+    // Snapshot of the stats of registered players, to be persisted.
+    pub fn stats_store(&self) -> Option<StatsStore> {
+        self.save_stats.then(|| {
+            StatsStore::new(
+                self.stats
+                    .iter()
+                    .filter(|(id, _)| !self.guests.contains(id))
+                    .map(|(&id, stats)| (id, stats.clone()))
+                    .collect(),
+            )
+        })
+    }
+
+    pub fn hero_stats(&self, id: &PlayerId) -> Option<&HeroStats> {
+        self.stats.get(id)
+    }
+
+    // Achievements unlocked by the hero in the last Achievement::TOAST_DURATION.
+    pub fn achievement_toasts(&self, id: &PlayerId) -> Vec<Achievement> {
+        self.achievement_toasts
+            .get(id)
+            .map(|toasts| {
+                toasts
+                    .iter()
+                    .filter(|(_, instant)| instant.elapsed() < Achievement::TOAST_DURATION)
+                    .map(|(achievement, _)| *achievement)
+                    .collect_vec()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn kill_feed(&self) -> Vec<&str> {
+        self.kill_feed
+            .iter()
+            .map(|(message, _)| message.as_str())
+            .collect_vec()
+    }
+
+    pub fn send_chat(
+        &mut self,
+        hero_id: &PlayerId,
+        channel: ChatChannel,
+        text: &str,
+    ) -> AppResult<()> {
+        let hero = self
+            .heros
+            .get(hero_id)
+            .ok_or_else(|| anyhow!("Hero not found"))?;
+        self.chat
+            .send(*hero_id, hero.name(), hero.maze_id(), channel, text)
+    }
+
+    pub fn chat_messages(&self, hero_id: &PlayerId) -> Vec<&ChatMessage> {
+        let Some(hero) = self.heros.get(hero_id) else {
+            return vec![];
+        };
+        self.chat.messages(hero.maze_id()).collect_vec()
+    }
+
+    pub fn subscribe_to_events(&self) -> tokio::sync::broadcast::Receiver<GameEvent> {
+        self.events.subscribe()
+    }
+
+    // Dispatches pending events to the game own subscribers. Events emitted
+    // while processing are processed too.
+    fn process_events(&mut self) {
+        loop {
+            let events = self.events.drain();
+            if events.is_empty() {
+                break;
+            }
+
+            for event in events.iter() {
+                log::debug!("{:?}", event);
+                self.update_stats(event);
+                self.check_achievements(event);
+                self.update_kill_feed(event);
+                self.update_replays(event);
+                self.update_ghosts(event);
+            }
+        }
+    }
+
+    fn update_stats(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::RunStarted { hero_id } => {
+                self.stats.entry(*hero_id).or_default().runs_started += 1;
+            }
+            GameEvent::HeroMoved { hero_id, .. } => {
+                self.stats.entry(*hero_id).or_default().distance_walked += 1;
+            }
+            GameEvent::HeroDied {
+                hero_id,
+                maze_id,
+                killer,
+            } => {
+                self.stats
+                    .entry(*hero_id)
+                    .or_default()
+                    .record_death(*maze_id, killer.as_deref());
+            }
+            GameEvent::PowerUpCollected {
+                hero_id, power_up, ..
+            } => {
+                self.stats
+                    .entry(*hero_id)
+                    .or_default()
+                    .record_power_up(*power_up);
+            }
+            GameEvent::RoomCleared {
+                hero_id,
+                maze_id,
+                duration,
+            } => {
+                self.stats
+                    .entry(*hero_id)
+                    .or_default()
+                    .record_clear(*maze_id, *duration);
+            }
+            GameEvent::AchievementUnlocked {
+                hero_id,
+                achievement,
+            } => {
+                self.stats.entry(*hero_id).or_default().unlock(*achievement);
+                self.achievement_toasts
+                    .entry(*hero_id)
+                    .or_default()
+                    .push((*achievement, Instant::now()));
+            }
+            _ => {}
+        }
+    }
+
+    fn check_achievements(&mut self, event: &GameEvent) {
+        let (hero_id, achievements) = match event {
+            GameEvent::PowerUpCollected { hero_id, .. } => {
+                let Some(hero) = self.heros.get(hero_id) else {
+                    return;
+                };
+                let memory_power_ups = hero
+                    .power_ups_collected_in_run()
+                    .filter(|&&power_up| power_up == PowerUp::Memory)
+                    .count();
+
+                if memory_power_ups >= Achievement::ELEPHANT_MEMORY_POWER_UPS {
+                    (hero_id, vec![Achievement::Elephant])
+                } else {
+                    return;
+                }
+            }
+            GameEvent::RoomCleared {
+                hero_id, maze_id, ..
+            } => {
+                let Some(hero) = self.heros.get(hero_id) else {
+                    return;
+                };
+
+                if *maze_id == Achievement::PURIST_MAZE_ID
+                    && hero.power_ups_collected_in_run().next().is_none()
+                {
+                    (hero_id, vec![Achievement::Purist])
+                } else {
+                    return;
+                }
+            }
+            GameEvent::ChaseEnded { hero_id, duration }
+                if *duration >= Achievement::MARATHONER_CHASE_DURATION =>
+            {
+                (hero_id, vec![Achievement::Marathoner])
+            }
+            GameEvent::Victory { hero_id, duration } => {
+                let mut achievements = vec![Achievement::Escapist];
+                if *duration < Achievement::SPEEDRUNNER_DURATION {
+                    achievements.push(Achievement::Speedrunner);
+                }
+                (hero_id, achievements)
+            }
+            _ => return,
+        };
+
+        let stats = self.stats.entry(*hero_id).or_default();
+        for achievement in achievements {
+            if !stats.achievements.contains(&achievement) {
+                self.events.emit(GameEvent::AchievementUnlocked {
+                    hero_id: *hero_id,
+                    achievement,
+                });
+            }
+        }
+    }
+
+    fn finish_replay(&mut self, hero_id: &PlayerId) {
+        if let Some(chunk) = self
+            .replays
+            .remove(hero_id)
+            .and_then(|mut replay| replay.take_chunk(true))
+        {
+            self.replay_chunks.push(chunk);
+        }
+    }
+
+    fn update_replays(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::RunStarted { hero_id } => {
+                self.finish_replay(hero_id);
+                if self.guests.contains(hero_id) {
+                    return;
+                }
+                if let Some(hero) = self.heros.get(hero_id) {
+                    let replay = Replay::new(
+                        hero,
+                        now_timestamp(),
+                        self.tick,
+                        Self::update_time_step(),
+                        self.config,
+                        self.mazes
+                            .iter()
+                            .map(|maze| maze.descriptor())
+                            .collect_vec(),
+                    );
+                    self.replays.insert(*hero_id, replay);
+                }
+            }
+            GameEvent::HeroLeft { hero_id } => self.finish_replay(hero_id),
+            _ => {}
+        }
+    }
+
+    fn record_replay_frames(&mut self) {
+        for (hero_id, replay) in self.replays.iter_mut() {
+            let Some(hero) = self.heros.get(hero_id) else {
+                continue;
+            };
+
+            let minotaurs = self.minotaur_rooms[hero.maze_id()]
+                .iter()
+                .filter_map(|id| self.minotaurs.get(id))
+                .map(|minotaur| MinotaurSnapshot::new(minotaur, *hero_id))
+                .collect_vec();
+
+            replay.record_frame(self.tick, hero.into(), minotaurs);
+            if let Some(chunk) = replay.take_chunk(false) {
+                self.replay_chunks.push(chunk);
+            }
+        }
+    }
+
+    // Parts of replays recorded since the last call, to be written to disk in order.
+    pub fn take_replay_chunks(&mut self) -> Vec<ReplayChunk> {
+        std::mem::take(&mut self.replay_chunks)
+    }
+
+    // Ends all the recordings in progress, so that they are returned by take_replay_chunks.
+    pub fn finish_replays(&mut self) {
+        for hero_id in self.replays.keys().copied().collect_vec() {
+            self.finish_replay(&hero_id);
+        }
+    }
+
+    fn best_ghost(&self, hero_id: &PlayerId, maze_id: usize) -> Option<&Ghost> {
+        self.ghosts
+            .get(hero_id)?
+            .get(&self.mazes[maze_id].random_seed())
+    }
+
+    fn update_ghosts(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::RunStarted { hero_id } | GameEvent::HeroLeft { hero_id } => {
+                self.ghost_runs.remove(hero_id);
+            }
+            GameEvent::RoomCleared {
+                hero_id,
+                maze_id,
+                duration,
+            } => {
+                let random_seed = self.mazes[*maze_id].random_seed();
+                let Some(run) = self.ghost_runs.remove(hero_id) else {
+                    return;
+                };
+                if run.random_seed != random_seed {
+                    return;
+                }
+
+                let best = self
+                    .best_ghost(hero_id, *maze_id)
+                    .map(|ghost| ghost.duration);
+                if best.is_none_or(|best| *duration < best) {
+                    let mut ghost = run.ghost;
+                    ghost.duration = *duration;
+                    ghost.timestamp = now_timestamp();
+                    self.ghosts
+                        .entry(*hero_id)
+                        .or_default()
+                        .insert(random_seed, ghost);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Records the path of heros in their room, and compares it to their best ghost.
+    fn record_ghost_runs(&mut self) {
+        for hero in self.heros.values() {
+            if !matches!(hero.state, HeroState::InMaze { .. }) {
+                continue;
+            }
+
+            let random_seed = self.mazes[hero.maze_id()].random_seed();
+            let elapsed = hero.elapsed_duration_in_maze();
+            let gap = self
+                .best_ghost(&hero.id(), hero.maze_id())
+                .and_then(|ghost| ghost.time_at(hero.position()))
+                .map(|time| elapsed.as_secs_f64() - time.as_secs_f64());
+
+            let run = self.ghost_runs.entry(hero.id()).or_default();
+            // Entering a room, or coming back to it, starts a new ghost.
+            if run.random_seed != random_seed {
+                *run = GhostRun {
+                    random_seed,
+                    ..Default::default()
+                };
+            }
+            run.ghost.record(elapsed, hero.position());
+            if gap.is_some() {
+                run.gap = gap;
+            }
+        }
+    }
+
+    // Seconds the hero is behind its best ghost in the current room, negative when ahead.
+    pub fn ghost_gap(&self, hero_id: &PlayerId) -> Option<f64> {
+        let hero = self.heros.get(hero_id)?;
+        self.best_ghost(hero_id, hero.maze_id())?;
+        self.ghost_runs.get(hero_id)?.gap
+    }
+
+    fn update_kill_feed(&mut self, event: &GameEvent) {
+        let hero_name = |hero_id: &PlayerId| {
+            self.heros
+                .get(hero_id)
+                .map(|hero| hero.name().to_string())
+                .unwrap_or_default()
+        };
+
+        let message = match event {
+            GameEvent::HeroDied {
+                hero_id,
+                maze_id,
+                killer: Some(killer),
+            } => format!(
+                "{} caught {} in r{}",
+                killer,
+                hero_name(hero_id),
+                maze_id + 1
+            ),
+            GameEvent::HeroDied {
+                hero_id,
+                maze_id,
+                killer: None,
+            } => format!("{} fell in spikes in r{}", hero_name(hero_id), maze_id + 1),
+            GameEvent::HeroRevived { hero_id, by } => {
+                format!("{} revived {}", hero_name(by), hero_name(hero_id))
+            }
+            GameEvent::PowerUpGiven {
+                hero_id,
+                to,
+                power_up,
+            } => format!(
+                "{} gave {} to {}",
+                hero_name(hero_id),
+                power_up.to_string().to_lowercase(),
+                hero_name(to)
+            ),
+            GameEvent::Victory { hero_id, .. } => format!("{} escaped!", hero_name(hero_id)),
+            _ => return,
+        };
+
+        self.kill_feed.push((message, Instant::now()));
+        if self.kill_feed.len() > Self::MAX_KILL_FEED_LENGTH {
+            self.kill_feed.remove(0);
+        }
+    }
+
+    pub fn top_heros(&self) -> &Vec<(PlayerId, String, usize, Duration)> {
+        &self.top_heros
+    }
+
+    pub fn top_minotaurs(&self) -> &Vec<(PlayerId, String, usize, usize)> {
+        &self.top_minotaurs
+    }
+
+    pub fn minotaurs_in_maze(&self, maze_id: usize) -> usize {
+        self.minotaur_rooms[maze_id].len()
+    }
+
+    pub fn interactions(&self) -> HeroInteractions {
+        self.interactions
+    }
+
+    pub fn set_interactions(&mut self, interactions: HeroInteractions) {
+        self.interactions = interactions;
+    }
+
+    // Living hero standing at the position, other than the given one.
+    fn hero_at(&self, maze_id: usize, position: Position, except: PlayerId) -> Option<PlayerId> {
+        self.hero_rooms[maze_id].iter().copied().find(|id| {
+            *id != except
+                && self.heros.get(id).is_some_and(|hero| {
+                    matches!(hero.state, HeroState::InMaze { .. }) && hero.position() == position
+                })
+        })
+    }
+
+    // Pushes the hero in front one tile further, if there is room. Shoved heros
+    // do not trigger traps or pick up items.
+    fn shove_hero(&mut self, hero_id: PlayerId) {
+        let Some(hero) = self.heros.get(&hero_id) else {
+            return;
+        };
+        if !self.interactions.shoving || !hero.can_move() {
+            return;
+        }
+
+        let maze_id = hero.maze_id();
+        let direction = hero.direction();
+        let Some(target_id) =
+            self.hero_at(maze_id, hero.position().into_direction(&direction), hero_id)
+        else {
+            return;
+        };
+        let Some(target) = self.heros.get(&target_id) else {
+            return;
+        };
+
+        let maze = &self.mazes[maze_id];
+        let new_position = target.position().into_direction(&direction);
+        let is_locked = maze
+            .door_at(new_position)
+            .is_some_and(|color| !target.has_key(color));
+        if !maze.is_walkable_position(new_position)
+            || !maze.is_move_allowed(target.position(), direction)
+            || is_locked
+            || self.hero_at(maze_id, new_position, target_id).is_some()
+        {
+            return;
+        }
+
+        if let Some(hero) = self.heros.get_mut(&hero_id) {
+            hero.spend_move();
+        }
+        if let Some(target) = self.heros.get_mut(&target_id) {
+            target.set_position(new_position);
+            target.update_past_visible_positions(
+                self.mazes[maze_id].get_and_cache_visible_positions(
+                    target.position(),
+                    target.direction(),
+                    target.view(),
+                ),
+            );
+            self.events.emit(GameEvent::HeroMoved {
+                hero_id: target_id,
+                maze_id,
+                position: new_position,
+            });
+        }
+    }
+
+    fn give_power_up(&mut self, hero_id: PlayerId) {
+        let Some(hero) = self.heros.get(&hero_id) else {
+            return;
+        };
+        if !self.interactions.trading {
+            return;
+        }
+        let Some(power_up) = hero.held_power_up() else {
+            return;
+        };
+
+        // Heros already at the maximum are skipped, so that the power up is not lost.
+        let position = hero.position();
+        let Some(target_id) = std::iter::once(position)
+            .chain(
+                [
+                    Direction::North,
+                    Direction::East,
+                    Direction::South,
+                    Direction::West,
+                ]
+                .iter()
+                .map(|direction| position.into_direction(direction)),
+            )
+            .filter_map(|position| self.hero_at(hero.maze_id(), position, hero_id))
+            .find(|id| {
+                self.heros
+                    .get(id)
+                    .is_some_and(|target| target.can_receive_power_up(power_up))
+            })
+        else {
+            return;
+        };
+
+        if self
+            .heros
+            .get_mut(&hero_id)
+            .and_then(|hero| hero.give_power_up())
+            .is_none()
+        {
+            return;
+        }
+        if let Some(target) = self.heros.get_mut(&target_id) {
+            target.receive_power_up(power_up);
+            self.events.emit(GameEvent::PowerUpGiven {
+                hero_id,
+                to: target_id,
+                power_up,
+            });
+        }
+    }
+
+    pub fn is_coop(&self) -> bool {
+        self.is_coop
+    }
+
+    fn teammates<'a>(&'a self, hero: &'a Hero) -> impl Iterator<Item = &'a Hero> {
+        self.heros
+            .values()
+            .filter(move |other| self.is_coop && other.id() != hero.id())
+    }
+
+    // Living teammates in the same room share what they currently see.
+    fn viewers<'a>(&'a self, hero: &'a Hero) -> Vec<&'a Hero> {
+        std::iter::once(hero)
+            .chain(
+                self.teammates(hero)
+                    .filter(|teammate| teammate.maze_id() == hero.maze_id() && !teammate.is_dead()),
+            )
+            .collect_vec()
+    }
+
+    // Current and best depth reached by the whole team, as room ids.
+    pub fn team_depth(&self) -> Option<(usize, usize)> {
+        if !self.is_coop {
+            return None;
+        }
+
+        let depth = self.current_team_depth()?;
+        Some((depth, self.team_best_depth.max(depth)))
+    }
+
+    fn current_team_depth(&self) -> Option<usize> {
+        self.heros
+            .values()
+            .map(|hero| {
+                if hero.has_won().is_some() {
+                    self.number_of_rooms()
+                } else {
+                    hero.maze_id()
+                }
+            })
+            .min()
+    }
+
+    // Time left for a teammate to revive a dead hero.
+    pub fn revive_window(&self, hero_id: &PlayerId) -> Option<Duration> {
+        if !self.is_coop {
+            return None;
+        }
+
+        match self.heros.get(hero_id)?.state {
+            HeroState::Dead { instant, .. } => {
+                Some(self.config.revive_grace().saturating_sub(instant.elapsed()))
+            }
+            _ => None,
+        }
+    }
+
+    // Dead heros whose spot a living teammate stands on come back to life,
+    // keeping their run timer.
+    fn revive_teammates(&mut self) {
+        let revive_grace = self.config.revive_grace();
+        let revived = self
+            .heros
+            .values()
+            .filter_map(|hero| {
+                let HeroState::Dead { duration, instant } = hero.state else {
+                    return None;
+                };
+                if instant.elapsed() > revive_grace {
+                    return None;
+                }
+
+                let reviver = self.teammates(hero).find(|other| {
+                    matches!(other.state, HeroState::InMaze { .. })
+                        && other.maze_id() == hero.maze_id()
+                        && other.position() == hero.position()
+                })?;
+                Some((
+                    hero.id(),
+                    reviver.id(),
+                    instant.checked_sub(duration).unwrap_or(instant),
+                ))
+            })
+            .collect_vec();
+
+        for (hero_id, by, started_at) in revived {
+            if let Some(hero) = self.heros.get_mut(&hero_id) {
+                hero.state = HeroState::InMaze {
+                    instant: started_at,
+                };
+                self.events.emit(GameEvent::HeroRevived { hero_id, by });
+            }
+        }
+    }
+
+    pub fn race(&self) -> Option<&Race> {
+        self.race.as_ref()
+    }
+
+    // Brings every hero back to the start and begins the countdown.
+    pub fn start_race(&mut self) {
+        if !self.race.as_ref().is_some_and(|race| race.can_start()) {
+            return;
+        }
+
+        for hero_id in self.heros.keys().copied().collect_vec() {
+            self.respawn_hero(hero_id);
+        }
+        self.race = Some(Race::Countdown {
+            instant: Instant::now(),
+        });
+        self.process_events();
+    }
+
+    fn respawn_hero(&mut self, hero_id: PlayerId) {
+        let Some(hero) = self.heros.get_mut(&hero_id) else {
+            return;
+        };
+
+        self.hero_rooms[hero.maze_id()].retain(|id| *id != hero_id);
+        self.hero_rooms[0].push(hero_id);
+
+        let maze = &mut self.mazes[0];
+        hero.reset(maze.hero_starting_position());
+        self.events.emit(GameEvent::RunStarted { hero_id });
+        let visible_positions =
+            maze.get_and_cache_visible_positions(hero.position(), hero.direction(), hero.view());
+        hero.update_past_visible_positions(visible_positions);
+    }
+
+    fn update_race(&mut self) {
+        match self.race {
+            Some(Race::Countdown { instant }) if instant.elapsed() >= Race::COUNTDOWN_DURATION => {
+                // Everyone starts together in a fresh labyrinth.
+                let (mazes, minotaurs, minotaur_rooms) = Self::generate_rooms(None, &self.config);
+                self.top_minotaurs_map
+                    .retain(|id, (_, _, kills)| *kills > 0 || !self.minotaurs.contains_key(id));
+                self.mazes = mazes;
+                self.minotaurs = minotaurs;
+                self.minotaur_rooms = minotaur_rooms;
+                self.prune_ghosts();
+                for minotaur in self.minotaurs.values() {
+                    self.top_minotaurs_map.insert(
+                        minotaur.id(),
+                        (minotaur.name().to_string(), minotaur.maze_id(), 0),
+                    );
+                }
+                self.update_top_minotaurs();
+
+                self.hero_rooms = vec![vec![]; self.mazes.len()];
+                for hero_id in self.heros.keys().copied().collect_vec() {
+                    self.mazes[0].increase_attempted();
+                    self.respawn_hero(hero_id);
+                    if let Some(hero) = self.heros.get_mut(&hero_id) {
+                        hero.state = HeroState::InMaze {
+                            instant: Instant::now(),
+                        };
+                    }
+                }
 
-        for maze in mazes.iter_mut() {
-            let mut maze_minotaurs = vec![];
-            for index in 0..maze.id {
-                let name = format!("{}#{}{}", random_minotaur_name(), maze.id, index);
-                let minotaur = maze.spawn_minotaur(name);
-                maze_minotaurs.push(minotaur.id());
-                minotaurs.insert(minotaur.id(), minotaur);
+                self.race = Some(Race::Running {
+                    instant: Instant::now(),
+                });
             }
-            minotaur_rooms[maze.id] = maze_minotaurs;
+            Some(Race::Running { instant }) => {
+                // Heros who joined during the race wait for the next one.
+                let racers = self
+                    .heros
+                    .values()
+                    .filter(|hero| hero.state != HeroState::WaitingToStart)
+                    .collect_vec();
+                let is_over = racers
+                    .iter()
+                    .all(|hero| hero.is_dead() || hero.has_won().is_some());
+
+                if is_over || instant.elapsed() >= Race::TIME_LIMIT {
+                    let results = racers
+                        .iter()
+                        .map(|hero| match hero.has_won() {
+                            Some(duration) => RaceResult {
+                                hero_id: hero.id(),
+                                name: hero.name().to_string(),
+                                has_exited: true,
+                                maze_id: self.number_of_rooms(),
+                                duration,
+                            },
+                            None => RaceResult {
+                                hero_id: hero.id(),
+                                name: hero.name().to_string(),
+                                has_exited: false,
+                                maze_id: hero.maze_id(),
+                                duration: hero.maze_entered_at(),
+                            },
+                        })
+                        .collect_vec();
+                    self.race = Some(Race::finish(results));
+                }
+            }
+            _ => {}
         }
+    }
 
-        Self {
-            mazes,
-            heros: HashMap::new(),
-            hero_rooms: [const { Vec::new() }; MAX_MAZE_ID],
-            taken_names: HashSet::new(),
-            top_heros_map: HashMap::new(),
-            top_heros: vec![],
-            minotaurs,
-            minotaur_rooms,
-            top_minotaurs_map: HashMap::new(),
-            top_minotaurs: vec![],
-        }
+    // Heros who exit the labyrinth are recorded as reaching this room id.
+    pub fn number_of_rooms(&self) -> usize {
+        self.mazes.len()
     }
 
-    pub fn top_heros(&self) -> &Vec<(PlayerId, String, usize, Duration)> {
-        &self.top_heros
+    pub fn config(&self) -> &GameConfig {
+        &self.config
     }
 
-    pub fn top_minotaurs(&self) -> &Vec<(PlayerId, String, usize, usize)> {
-        &self.top_minotaurs
+    pub fn instance(&self) -> &GameInstance {
+        &self.instance
     }
 
-    pub fn minotaurs_in_maze(&self, maze_id: usize) -> usize {
-        self.minotaur_rooms[maze_id].len()
+    pub fn heros_in_maze(&self, maze_id: usize) -> Vec<&Hero> {
+        self.hero_rooms[maze_id]
+            .iter()
+            .filter_map(|id| self.heros.get(id))
+            .collect_vec()
+    }
+
+    // Hero ids sorted by name, so that spectators cycle through them in a stable order.
+    pub fn hero_ids(&self) -> Vec<PlayerId> {
+        self.heros
+            .values()
+            .sorted_by(|a, b| a.name().cmp(b.name()))
+            .map(|hero| hero.id())
+            .collect_vec()
     }
 
     pub fn alarm_level(&self, hero_id: &PlayerId) -> (AlarmLevel, usize) {
@@ -209,16 +1339,25 @@ impl Game {
         (AlarmLevel::NoMinotaurs, usize::MAX)
     }
 
-    pub fn add_player(&mut self, player_id: PlayerId, name: &str) {
-        let rng = &mut rand::thread_rng();
-        let mut player_name = to_player_name(rng, name);
-        while self.taken_names.contains(&player_name) {
-            player_name = to_player_name(rng, name);
+    // Names are unique across accounts and guests, see AccountStore.
+    pub fn add_player(&mut self, player_id: PlayerId, name: &str, is_registered: bool) {
+        let player_name = name.to_string();
+
+        if !is_registered {
+            self.guests.insert(player_id);
         }
-        self.taken_names.insert(player_name.clone());
+        self.events
+            .emit(GameEvent::HeroJoined { hero_id: player_id });
+        self.events
+            .emit(GameEvent::RunStarted { hero_id: player_id });
 
         let maze = &mut self.mazes[0];
-        let mut hero = Hero::new(player_id, player_name, maze.hero_starting_position());
+        let mut hero = Hero::new(
+            player_id,
+            player_name,
+            maze.hero_starting_position(),
+            self.config.hero,
+        );
         maze.increase_attempted();
 
         let visible_positions =
@@ -227,22 +1366,60 @@ impl Game {
 
         self.hero_rooms[maze.id].push(hero.id());
 
-        self.top_heros_map.insert(
-            hero.id(),
-            (
+        // Returning players keep their personal best.
+        if let Entry::Vacant(entry) = self.top_heros_map.entry(hero.id()) {
+            entry.insert((
                 hero.name().to_string(),
                 0,
                 hero.elapsed_duration_from_start(),
-            ),
-        );
-
-        self.update_top_heros();
+            ));
+            self.record_timestamps.insert(hero.id(), now_timestamp());
+            self.prune_records();
+        }
 
         self.heros.insert(player_id, hero);
+        self.process_events();
     }
 
     pub fn remove_player(&mut self, player_id: &PlayerId) {
-        self.heros.remove(&player_id);
+        if let Some(hero) = self.heros.remove(player_id) {
+            self.hero_rooms[hero.maze_id()].retain(|id| id != player_id);
+            self.events.emit(GameEvent::HeroLeft {
+                hero_id: *player_id,
+            });
+            self.process_events();
+        }
+
+        if self.guests.remove(player_id) {
+            self.stats.remove(player_id);
+            self.ghosts.remove(player_id);
+        }
+        self.achievement_toasts.remove(player_id);
+        self.chase_instants.remove(player_id);
+        self.detached.remove(player_id);
+        self.chat.remove_player(player_id);
+    }
+
+    // Keeps the hero in the game while its player reconnects. Returns false if there is no hero.
+    pub fn detach_player(&mut self, player_id: &PlayerId) -> bool {
+        if !self.heros.contains_key(player_id) {
+            return false;
+        }
+        self.detached.insert(*player_id, Instant::now());
+        true
+    }
+
+    // Returns whether a hero was waiting for the player in this game.
+    pub fn reattach_player(&mut self, player_id: &PlayerId) -> bool {
+        self.detached.remove(player_id).is_some()
+    }
+
+    pub fn is_detached(&self, player_id: &PlayerId) -> bool {
+        self.detached.contains_key(player_id)
+    }
+
+    pub fn has_detached_players(&self) -> bool {
+        !self.detached.is_empty()
     }
 
     pub fn get_hero(&self, id: &PlayerId) -> Option<&Hero> {
@@ -262,18 +1439,57 @@ impl Game {
     }
 
     pub fn update(&mut self) {
+        for maze in self.mazes.iter_mut() {
+            maze.update();
+        }
+
+        let time_step = self.last_update.elapsed();
+        self.last_update = Instant::now();
+        self.tick += 1;
+
+        // Heros not reclaimed in time are removed.
+        let reconnect_grace = self.config.reconnect_grace();
+        let expired = self
+            .detached
+            .iter()
+            .filter(|(_, instant)| instant.elapsed() >= reconnect_grace)
+            .map(|(&id, _)| id)
+            .collect_vec();
+        for player_id in expired {
+            self.remove_player(&player_id);
+        }
+
+        self.update_race();
+        // Racing heros only respawn at the start of the next race.
+        let can_respawn = self.race.is_none();
+
+        // Dead teammates wait to be revived before respawning.
+        let respawn_interval = if self.is_coop {
+            self.revive_teammates();
+            if let Some(depth) = self.current_team_depth() {
+                self.team_best_depth = self.team_best_depth.max(depth);
+            }
+            self.config.revive_grace()
+        } else {
+            self.config.respawn_interval()
+        };
+
         // Update heros
         for hero in self.heros.values_mut() {
+            self.stats.entry(hero.id()).or_default().play_time += time_step;
+
             match hero.state {
                 HeroState::WaitingToStart | HeroState::InMaze { .. } => {}
                 HeroState::Dead { instant, .. } => {
-                    if instant.elapsed() > Self::RESPAWN_INTERVAL {
+                    if can_respawn && instant.elapsed() > respawn_interval {
                         // Move hero between rooms
                         self.hero_rooms[hero.maze_id()].retain(|id| *id != hero.id());
                         self.hero_rooms[0].push(hero.id());
 
                         let maze = &mut self.mazes[0];
                         hero.reset(maze.hero_starting_position());
+                        self.events
+                            .emit(GameEvent::RunStarted { hero_id: hero.id() });
                         let visible_positions = maze.get_and_cache_visible_positions(
                             hero.position(),
                             hero.direction(),
@@ -284,13 +1500,15 @@ impl Game {
                 }
 
                 HeroState::Victory { instant, .. } => {
-                    if instant.elapsed() > Self::RESPAWN_INTERVAL {
+                    if can_respawn && instant.elapsed() > self.config.respawn_interval() {
                         // Move hero between rooms
                         self.hero_rooms[hero.maze_id()].retain(|id| *id != hero.id());
                         self.hero_rooms[0].push(hero.id());
 
                         let maze = &mut self.mazes[0];
                         hero.reset(maze.hero_starting_position());
+                        self.events
+                            .emit(GameEvent::RunStarted { hero_id: hero.id() });
                         let visible_positions = maze.get_and_cache_visible_positions(
                             hero.position(),
                             hero.direction(),
@@ -320,6 +1538,7 @@ impl Game {
                 .values()
                 .filter(|hero| {
                     !hero.is_dead()
+                        && !self.detached.contains_key(&hero.id())
                         && hero.maze_id() == maze_id
                         && visible_positions.contains(&hero.position())
                 })
@@ -334,6 +1553,7 @@ impl Game {
                     hero.maze_id() == maze_id
                         && hero.position() == minotaur.position()
                         && !hero.is_dead()
+                        && !self.detached.contains_key(&hero.id())
                 })
                 .map(|hero| hero.id())
                 .collect_vec();
@@ -345,13 +1565,22 @@ impl Game {
                             hero.state = HeroState::Dead {
                                 duration: instant.elapsed(),
                                 instant: Instant::now(),
-                            }
+                            };
+                            self.events.emit(GameEvent::HeroDied {
+                                hero_id: hero.id(),
+                                maze_id,
+                                killer: Some(minotaur.name().to_string()),
+                            });
                         }
                         _ => {}
                     }
                 }
             }
 
+            if catched_heros.is_empty() {
+                continue;
+            }
+
             minotaur.kills += catched_heros.len();
             self.top_minotaurs_map.insert(
                 minotaur.id(),
@@ -361,12 +1590,49 @@ impl Game {
                     minotaur.kills,
                 ),
             );
+            self.record_timestamps
+                .insert(minotaur.id(), now_timestamp());
             should_update_top_minotaurs = true;
         }
 
         if should_update_top_minotaurs {
-            self.update_top_minotaurs();
+            self.prune_records();
+        }
+
+        for toasts in self.achievement_toasts.values_mut() {
+            toasts.retain(|(_, instant)| instant.elapsed() < Achievement::TOAST_DURATION);
+        }
+
+        self.kill_feed
+            .retain(|(_, instant)| instant.elapsed() < Self::KILL_FEED_DURATION);
+        self.chat.update();
+
+        for hero in self.heros.values() {
+            let is_chased = !hero.is_dead()
+                && self.minotaur_rooms[hero.maze_id()].iter().any(|id| {
+                    self.minotaurs
+                        .get(id)
+                        .is_some_and(|minotaur| minotaur.is_chasing(hero.id()))
+                });
+
+            if is_chased {
+                self.chase_instants
+                    .entry(hero.id())
+                    .or_insert_with(Instant::now);
+            } else if let Some(instant) = self.chase_instants.remove(&hero.id()) {
+                if !hero.is_dead() {
+                    self.events.emit(GameEvent::ChaseEnded {
+                        hero_id: hero.id(),
+                        duration: instant.elapsed(),
+                    });
+                }
+            }
         }
+
+        // Process events first, so that replays of new runs start from their first frame.
+        self.process_events();
+        self.record_replay_frames();
+        self.record_ghost_runs();
     }
 
     pub fn image_char_overrides(
@@ -383,8 +1649,17 @@ impl Game {
         let maze = &self.mazes[hero.maze_id()];
 
         // Override empty positions.
-        let visible_positions =
-            maze.get_cached_visible_positions(hero.position(), hero.direction(), hero.view());
+        let visible_positions = self
+            .viewers(hero)
+            .iter()
+            .flat_map(|viewer| {
+                maze.get_cached_visible_positions(
+                    viewer.position(),
+                    viewer.direction(),
+                    viewer.view(),
+                )
+            })
+            .collect::<HashSet<Position>>();
         let mut override_positions = visible_positions
             .iter()
             .filter(|(x, y)| {
@@ -395,6 +1670,60 @@ impl Game {
             .map(|&(x, y)| ((x as u32, y as u32), '·'))
             .collect::<HashMap<(u32, u32), char>>();
 
+        // Traps and collapsed positions are only revealed when currently in view.
+        for (&(x, y), trap) in maze.traps().iter() {
+            if visible_positions.contains(&(x, y)) {
+                override_positions.insert((x as u32, y as u32), trap.glyph());
+            }
+        }
+
+        for &(x, y) in maze.teleporters().keys() {
+            if visible_positions.contains(&(x, y)) {
+                override_positions.insert((x as u32, y as u32), Maze::teleporter_glyph());
+            }
+        }
+
+        for (&(x, y), direction) in maze.one_way_gates().iter() {
+            if visible_positions.contains(&(x, y)) {
+                override_positions.insert((x as u32, y as u32), Maze::gate_glyph(*direction));
+            }
+        }
+
+        // Marks cover a whole character, so they are placed on its top position.
+        for &(x, y) in hero.marks_in_maze().iter() {
+            if visible_positions.contains(&(x, y)) {
+                override_positions.insert((x as u32, (y - y % 2) as u32), Hero::MARK_GLYPH);
+            }
+        }
+
+        for other_hero in self.heros.values() {
+            if other_hero.id() == hero.id()
+                || other_hero.maze_id() != hero.maze_id()
+                || !other_hero.share_marks
+            {
+                continue;
+            }
+
+            for &(x, y) in other_hero.marks_in_maze().iter() {
+                if visible_positions.contains(&(x, y)) {
+                    override_positions
+                        .insert((x as u32, (y - y % 2) as u32), Hero::SHARED_MARK_GLYPH);
+                }
+            }
+        }
+
+        // Pings are shown to nearby heros even out of sight.
+        for ping in self.chat.pings_near(hero.maze_id(), hero.position()) {
+            let (x, y) = ping.position;
+            override_positions.insert((x as u32, (y - y % 2) as u32), ping.glyph());
+        }
+
+        for &(x, y) in maze.collapsed_positions() {
+            if visible_positions.contains(&(x, y)) {
+                override_positions.insert((x as u32, y as u32), Trap::collapsed_glyph());
+            }
+        }
+
         for &(x, y) in maze.entrance_positions().iter() {
             if !visible_positions.contains(&(x, y)) {
                 continue;
@@ -404,7 +1733,7 @@ impl Game {
                 for (idx, c) in (maze.id + 1 - 1).to_string().chars().enumerate() {
                     override_positions.insert((x as u32 + idx as u32 + 1, y as u32), c);
                 }
-                override_positions.insert((x as u32, y as u32), '←');
+                override_positions.insert((x as u32, y as u32), Maze::entrance_glyph());
             }
         }
 
@@ -416,12 +1745,60 @@ impl Game {
             for (idx, c) in (maze.id + 1 + 1).to_string().chars().rev().enumerate() {
                 override_positions.insert((x as u32 - idx as u32 - 1, y as u32), c);
             }
-            override_positions.insert((x as u32, y as u32), '→');
+            override_positions.insert((x as u32, y as u32), Maze::exit_glyph());
         }
 
         Ok(override_positions)
     }
 
+    // Full view of a room with all its entities, regardless of what heros can see.
+    pub fn draw_room(&self, maze_id: usize) -> RgbaImage {
+        let maze = &self.mazes[maze_id];
+        let mut image = maze.image().clone();
+
+        if let Some((x, y)) = maze.power_up_position {
+            image.put_pixel(x as u32, y as u32, GameColors::POWER_UP);
+        }
+
+        for source in maze.light_sources().iter() {
+            let (lx, ly) = source.position;
+            let color = match source.kind {
+                LightSourceKind::Torch => GameColors::TORCH,
+                LightSourceKind::Flare { .. } => GameColors::FLARE,
+            };
+            image.put_pixel(lx as u32, ly as u32, color);
+        }
+
+        for (&(dx, dy), color) in maze.doors().iter() {
+            image.put_pixel(dx as u32, dy as u32, color.door_rgba());
+        }
+
+        for (color, &(kx, ky)) in maze.keys().iter() {
+            image.put_pixel(kx as u32, ky as u32, color.rgba());
+        }
+
+        for minotaur_id in self.minotaur_rooms[maze_id].iter() {
+            if let Some(minotaur) = self.get_minotaur(minotaur_id) {
+                let (mx, my) = minotaur.position();
+                let color = if minotaur.is_chasing_someone() {
+                    GameColors::CHASING_MINOTAUR
+                } else {
+                    GameColors::MINOTAUR
+                };
+                image.put_pixel(mx as u32, my as u32, color);
+            }
+        }
+
+        for hero_id in self.hero_rooms[maze_id].iter() {
+            if let Some(hero) = self.get_hero(hero_id) {
+                let (hx, hy) = hero.position();
+                image.put_pixel(hx as u32, hy as u32, GameColors::HERO);
+            }
+        }
+
+        image
+    }
+
     pub fn draw(&self, player_id: PlayerId) -> AppResult<RgbaImage> {
         if let Some(hero) = self.heros.get(&player_id) {
             let (x, y) = hero.position();
@@ -434,31 +1811,57 @@ impl Game {
             let mut player_image =
                 RgbaImage::from_pixel(maze_image.width(), maze_image.height(), Rgba([0; 4]));
 
-            let visible_positions =
-                maze.get_cached_visible_positions(hero.position(), hero.direction(), hero.view());
-
-            for (&(dx, dy), instant) in hero.past_visible_positions().iter() {
+            let viewers = self.viewers(hero);
+
+            // How close each visible position is to its nearest viewer.
+            let mut visibility: HashMap<Position, f64> = HashMap::new();
+            for viewer in viewers.iter() {
+                for position in maze.get_cached_visible_positions(
+                    viewer.position(),
+                    viewer.direction(),
+                    viewer.view(),
+                ) {
+                    let closeness =
+                        1.0 - viewer.position().distance(position) / viewer.view().radius() as f64;
+                    let entry = visibility.entry(position).or_insert(closeness);
+                    *entry = entry.max(closeness);
+                }
+            }
+            let visible_positions: HashSet<Position> = visibility.keys().copied().collect();
+
+            let team_only_positions = visible_positions
+                .iter()
+                .filter(|position| !hero.past_visible_positions().contains_key(position))
+                .map(|position| (position, None));
+
+            for (&(dx, dy), instant) in hero
+                .past_visible_positions()
+                .iter()
+                .map(|(position, instant)| (position, Some(instant)))
+                .chain(team_only_positions)
+            {
                 // Each position in the past_visible_positions is copied from the maze_image, with alpha channel depending on the time passed.
                 let base_color = maze_image.get_pixel(dx as u32, dy as u32);
 
                 let is_valid = maze.is_valid_position((dx, dy));
 
                 let base_alpha = if is_valid { 0 } else { 125 };
-                let mut alpha = if instant.elapsed() < hero.past_visibility_duration() {
-                    base_alpha
-                        - (base_alpha as f64 * instant.elapsed().as_millis() as f64
-                            / hero.past_visibility_duration().as_millis() as f64)
-                            as u8
-                } else {
-                    0
+                let mut alpha = match instant {
+                    Some(instant) if instant.elapsed() < hero.past_visibility_duration() => {
+                        base_alpha
+                            - (base_alpha as f64 * instant.elapsed().as_millis() as f64
+                                / hero.past_visibility_duration().as_millis() as f64)
+                                as u8
+                    }
+                    _ => 0,
                 };
 
-                if visible_positions.contains(&(dx, dy)) {
-                    // Each position in the visible_positions is copied from the maze_image, with alpha channel depending on the distance from the hero.
-                    let distance = hero.position().distance((dx, dy));
-                    alpha += ((255.0 - alpha as f64)
-                        * (1.0 - distance / hero.view().radius() as f64))
-                        as u8;
+                if let Some(closeness) = visibility.get(&(dx, dy)) {
+                    // Each position in the visible_positions is copied from the maze_image, with alpha channel depending on the distance from the closest viewer and on how lit the position is.
+                    let light = maze
+                        .light_level((dx, dy))
+                        .max(LightSource::MIN_DRAWN_INTENSITY);
+                    alpha += ((255.0 - alpha as f64) * closeness * light) as u8;
                 }
 
                 let pixel = Rgba([base_color[0], base_color[1], base_color[2], alpha]);
@@ -474,13 +1877,43 @@ impl Game {
                 }
             }
 
+            // Add light sources
+            for source in maze.light_sources().iter() {
+                let (lx, ly) = source.position;
+                if visible_positions.contains(&(lx, ly)) {
+                    let color = match source.kind {
+                        LightSourceKind::Torch => GameColors::TORCH,
+                        LightSourceKind::Flare { .. } => GameColors::FLARE,
+                    };
+                    player_image.put_pixel(lx as u32, ly as u32, color);
+                }
+            }
+
+            // Add closed doors and keys which have not been picked up yet.
+            for (&(dx, dy), color) in maze.doors().iter() {
+                if !hero.has_key(*color) && visible_positions.contains(&(dx, dy)) {
+                    player_image.put_pixel(dx as u32, dy as u32, color.door_rgba());
+                }
+            }
+
+            for (color, &(kx, ky)) in maze.keys().iter() {
+                if !hero.has_key(*color) && visible_positions.contains(&(kx, ky)) {
+                    player_image.put_pixel(kx as u32, ky as u32, color.rgba());
+                }
+            }
+
             // Add other heros position
             for (p_id, any_hero) in self.heros.iter() {
                 if *p_id != player_id {
                     if any_hero.maze_id() == hero.maze_id() {
                         let (ax, ay) = any_hero.position();
                         if visible_positions.contains(&(ax, ay)) {
-                            player_image.put_pixel(ax as u32, ay as u32, GameColors::OTHER_HERO);
+                            let color = if self.is_coop && any_hero.is_dead() {
+                                GameColors::DEAD_TEAMMATE
+                            } else {
+                                GameColors::OTHER_HERO
+                            };
+                            player_image.put_pixel(ax as u32, ay as u32, color);
                         }
                     }
                 }
@@ -505,6 +1938,14 @@ impl Game {
                 }
             }
 
+            // Add the best ghost of the hero in this room
+            if let Some(position) = self
+                .best_ghost(&player_id, maze_id)
+                .and_then(|ghost| ghost.position_at(hero.elapsed_duration_in_maze()))
+            {
+                player_image.put_pixel(position.0 as u32, position.1 as u32, GameColors::GHOST);
+            }
+
             // Add hero position
             player_image.put_pixel(x as u32, y as u32, GameColors::HERO);
 
@@ -514,12 +1955,55 @@ impl Game {
     }
 
     pub fn handle_command(&mut self, command: &HeroCommand, hero_id: PlayerId) {
+        // Blocking heros are checked before borrowing the moving one.
+        let is_blocked = match (command, self.heros.get(&hero_id)) {
+            (HeroCommand::Move { direction }, Some(hero)) if self.interactions.blocking => self
+                .hero_at(
+                    hero.maze_id(),
+                    hero.position().into_direction(direction),
+                    hero_id,
+                )
+                .is_some(),
+            _ => false,
+        };
+
         let hero = if let Some(hero) = self.heros.get_mut(&hero_id) {
             hero
         } else {
             return;
         };
 
+        // Racing heros can only move and act once the race has started, and heros who
+        // joined during the race wait for the next one. Meanwhile they can still
+        // change their view, mark the maze and ping.
+        if let Some(race) = self.race.as_ref() {
+            if !race.is_running() || hero.state == HeroState::WaitingToStart {
+                match command {
+                    HeroCommand::CycleUiOptions => hero.ui_options = hero.ui_options.next(),
+                    HeroCommand::DropMark => {
+                        hero.drop_mark();
+                    }
+                    HeroCommand::EraseMark => {
+                        hero.erase_mark();
+                    }
+                    HeroCommand::ToggleShareMarks => hero.share_marks = !hero.share_marks,
+                    HeroCommand::Ping { kind } => self.chat.ping(
+                        hero_id,
+                        *kind,
+                        hero.maze_id(),
+                        hero.position(),
+                        hero.direction(),
+                    ),
+                    _ => {}
+                }
+                return;
+            }
+        }
+
+        if let Some(replay) = self.replays.get_mut(&hero_id) {
+            replay.record_command(*command);
+        }
+
         if hero.state == HeroState::WaitingToStart {
             hero.state = HeroState::InMaze {
                 instant: Instant::now(),
@@ -557,7 +2041,15 @@ impl Game {
 
                         let (new_x, new_y) = hero.position().into_direction(direction);
 
-                        if !self.mazes[maze_id].is_valid_position((new_x, new_y)) {
+                        let is_locked = self.mazes[maze_id]
+                            .door_at((new_x, new_y))
+                            .is_some_and(|color| !hero.has_key(color));
+
+                        if !self.mazes[maze_id].is_walkable_position((new_x, new_y))
+                            || !self.mazes[maze_id].is_move_allowed(hero.position(), *direction)
+                            || is_locked
+                            || is_blocked
+                        {
                             hero.update_past_visible_positions(
                                 self.mazes[maze_id].get_and_cache_visible_positions(
                                     hero.position(),
@@ -569,14 +2061,70 @@ impl Game {
                         }
 
                         hero.set_position((new_x, new_y));
+                        self.events.emit(GameEvent::HeroMoved {
+                            hero_id,
+                            maze_id,
+                            position: hero.position(),
+                        });
+
                         if let Some(position) = self.mazes[maze_id].power_up_position {
                             if position == hero.position()
                                 && hero.power_up_collected_in_maze().is_none()
                             {
                                 hero.apply_power_up();
+                                if let Some(&power_up) = hero.power_up_collected_in_maze() {
+                                    self.events.emit(GameEvent::PowerUpCollected {
+                                        hero_id,
+                                        maze_id,
+                                        power_up,
+                                    });
+                                }
                             }
                         }
 
+                        if let Some(color) = self.mazes[maze_id].key_at(hero.position()) {
+                            hero.pick_up_key(color);
+                        }
+
+                        if let Some(trap) = self.mazes[maze_id].trap_at(hero.position()) {
+                            self.events.emit(GameEvent::TrapTriggered {
+                                hero_id,
+                                maze_id,
+                                trap,
+                            });
+                            match trap {
+                                Trap::SpikePit => {
+                                    hero.state = HeroState::Dead {
+                                        duration: instant.elapsed(),
+                                        instant: Instant::now(),
+                                    };
+                                    self.events.emit(GameEvent::HeroDied {
+                                        hero_id,
+                                        maze_id,
+                                        killer: None,
+                                    });
+                                }
+                                Trap::SlowTile => hero.slow_down(Trap::SLOW_DURATION),
+                                Trap::AlarmPlate => {
+                                    for minotaur_id in self.minotaur_rooms[maze_id].iter() {
+                                        if let Some(minotaur) = self.minotaurs.get_mut(minotaur_id)
+                                        {
+                                            minotaur.alert(hero.position());
+                                        }
+                                    }
+                                }
+                                Trap::CollapsingFloor => {
+                                    self.mazes[maze_id].collapse_around(hero.position());
+                                }
+                            }
+                        }
+
+                        if let Some(destination) =
+                            self.mazes[maze_id].teleporter_destination(hero.position())
+                        {
+                            hero.set_position(destination);
+                        }
+
                         // Transition between rooms
                         if self.mazes[maze_id].is_entrance_position(hero.position()) && maze_id > 0
                         {
@@ -584,13 +2132,18 @@ impl Game {
                             self.mazes[maze_id].decrease_attempted();
                             self.mazes[to].decrease_passed();
                             hero.set_maze_id(to);
+                            self.events.emit(GameEvent::RoomEntered {
+                                hero_id,
+                                from: maze_id,
+                                to,
+                            });
 
                             // Move hero between rooms
                             self.hero_rooms[maze_id].retain(|id| *id != hero.id());
                             self.hero_rooms[to].push(hero.id());
 
                             // If hero acquired max vision in this maze, reduce it by one.
-                            if hero.vision() == Hero::MAX_VISION {
+                            if hero.vision() == hero.max_vision() {
                                 hero.decrease_vision();
                             }
 
@@ -604,23 +2157,37 @@ impl Game {
                             }
                         } else if self.mazes[maze_id].is_exit_position(hero.position()) {
                             let to = maze_id + 1;
+                            self.events.emit(GameEvent::RoomCleared {
+                                hero_id,
+                                maze_id,
+                                duration: hero.elapsed_duration_in_maze(),
+                            });
                             self.mazes[maze_id].increase_passed();
 
                             // Move hero between rooms
                             self.hero_rooms[maze_id].retain(|id| *id != hero.id());
 
-                            if to == MAX_MAZE_ID {
+                            if to == self.mazes.len() {
                                 hero.state = HeroState::Victory {
                                     duration: instant.elapsed(),
                                     instant: Instant::now(),
                                 };
+                                self.events.emit(GameEvent::Victory {
+                                    hero_id,
+                                    duration: instant.elapsed(),
+                                });
                             } else {
                                 hero.set_maze_id(to);
+                                self.events.emit(GameEvent::RoomEntered {
+                                    hero_id,
+                                    from: maze_id,
+                                    to,
+                                });
                                 self.hero_rooms[to].push(hero.id());
                                 self.mazes[to].increase_attempted();
 
                                 // If hero acquired max vision in this maze, reduce it by one.
-                                if hero.vision() == Hero::MAX_VISION {
+                                if hero.vision() == hero.max_vision() {
                                     hero.decrease_vision();
                                 }
 
@@ -681,25 +2248,77 @@ impl Game {
                     }
 
                     HeroCommand::CycleUiOptions => hero.ui_options = hero.ui_options.next(),
+
+                    HeroCommand::DropMark => {
+                        hero.drop_mark();
+                    }
+
+                    HeroCommand::EraseMark => {
+                        hero.erase_mark();
+                    }
+
+                    HeroCommand::ToggleShareMarks => hero.share_marks = !hero.share_marks,
+
+                    HeroCommand::Shove => self.shove_hero(hero_id),
+
+                    HeroCommand::GivePowerUp => self.give_power_up(hero_id),
+
+                    HeroCommand::Ping { kind } => {
+                        self.chat
+                            .ping(hero_id, *kind, maze_id, hero.position(), hero.direction())
+                    }
+
+                    HeroCommand::DropFlare => {
+                        if hero.use_flare() {
+                            self.mazes[maze_id].drop_flare(hero.position());
+                            hero.update_past_visible_positions(
+                                self.mazes[maze_id].get_and_cache_visible_positions(
+                                    hero.position(),
+                                    hero.direction(),
+                                    hero.view(),
+                                ),
+                            );
+                        }
+                    }
                 }
             }
             _ => {}
         }
 
         self.update_hero_record(hero_id);
+        self.process_events();
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Game, MAX_MAZE_ID};
-    use crate::{game::utils::to_player_name, PlayerId};
+    use super::{Game, GameConfig, GameStores, HeroInteractions, HeroState, Race, MAX_MAZE_ID};
+    use crate::{
+        game::{
+            utils::to_player_name, Direction, Entity, GameEvent, Ghost, HeroCommand, HeroConfig,
+            PowerUp, RoomScaling,
+        },
+        PlayerId,
+    };
+    use itertools::Itertools;
     use rand::Rng;
-    use std::time::Duration;
+    use std::{
+        collections::HashMap,
+        time::{Duration, Instant},
+    };
+
+    // Adds two guest heros to the game, returning their ids.
+    fn add_two_heros(game: &mut Game) -> (PlayerId, PlayerId) {
+        let first_id = PlayerId::new_v4();
+        let second_id = PlayerId::new_v4();
+        game.add_player(first_id, "first", false);
+        game.add_player(second_id, "second", false);
+        (first_id, second_id)
+    }
 
     #[test]
     fn test_top_heros() {
-        let mut game = Game::new();
+        let mut game = Game::new(GameConfig::default(), GameStores::default());
 
         let rng = &mut rand::thread_rng();
 
@@ -722,4 +2341,323 @@ mod tests {
             assert!(maze_id > next_maze_id || timer <= next_timer);
         }
     }
+
+    #[test]
+    fn test_events_update_stats_and_subscribers() {
+        let mut game = Game::new(GameConfig::default(), GameStores::default());
+        let mut receiver = game.subscribe_to_events();
+
+        let player_id = PlayerId::new_v4();
+        game.add_player(player_id, "name", false);
+
+        assert_eq!(
+            receiver.try_recv(),
+            Ok(GameEvent::HeroJoined { hero_id: player_id })
+        );
+        assert_eq!(
+            receiver.try_recv(),
+            Ok(GameEvent::RunStarted { hero_id: player_id })
+        );
+        assert_eq!(
+            game.hero_stats(&player_id).map(|stats| stats.runs_started),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_replay_records_only_changes() {
+        let mut game = Game::new(GameConfig::default(), GameStores::default());
+        let player_id = PlayerId::new_v4();
+        game.add_player(player_id, "name", true);
+        // Guests are not recorded.
+        let guest_id = PlayerId::new_v4();
+        game.add_player(guest_id, "guest", false);
+        assert!(!game.replays.contains_key(&guest_id));
+
+        // There are no minotaurs in the first maze, so only the first frame is recorded.
+        game.update();
+        game.update();
+        let replay = game
+            .replays
+            .get(&player_id)
+            .expect("Replay should be recording");
+        assert_eq!(replay.frames.len(), 1);
+        assert!(!replay.is_worth_saving());
+
+        game.handle_command(&HeroCommand::TurnClockwise, player_id);
+        game.update();
+        let replay = game
+            .replays
+            .get(&player_id)
+            .expect("Replay should be recording");
+        assert_eq!(replay.frames.len(), 2);
+        assert_eq!(replay.frames[1].commands, vec![HeroCommand::TurnClockwise]);
+        assert!(replay.is_worth_saving());
+        let replay = replay.clone();
+
+        game.remove_player(&player_id);
+        assert_eq!(game.take_replay_chunks().len(), 1);
+
+        // Replays rebuild the same labyrinth and hero state.
+        let mut replay_game = Game::from_replay(&replay).expect("Should build replay game");
+        let snapshot = replay.frames[1]
+            .hero
+            .clone()
+            .expect("Hero should have turned");
+        replay_game.set_replay_state(player_id, &snapshot, Duration::ZERO, &[]);
+        let hero = replay_game
+            .get_hero(&player_id)
+            .expect("Replay hero should exist");
+        assert_eq!(hero.direction(), snapshot.direction);
+        assert_eq!(replay_game.mazes[0].image(), game.mazes[0].image());
+        assert_eq!(replay_game.config, game.config);
+    }
+
+    #[test]
+    fn test_race() {
+        let mut game = Game::new_race("123456".to_string(), GameConfig::default());
+        let (first_id, second_id) = add_two_heros(&mut game);
+
+        // Heros cannot start before the race, but can still change their view.
+        let ui_options = game
+            .get_hero(&first_id)
+            .expect("Hero should exist")
+            .ui_options;
+        game.handle_command(&HeroCommand::TurnClockwise, first_id);
+        game.handle_command(&HeroCommand::CycleUiOptions, first_id);
+        let hero = game.get_hero(&first_id).expect("Hero should exist");
+        assert_eq!(hero.state, HeroState::WaitingToStart);
+        assert_ne!(hero.ui_options, ui_options);
+
+        game.start_race();
+        assert!(matches!(game.race(), Some(Race::Countdown { .. })));
+        game.race = Some(Race::Countdown {
+            instant: Instant::now() - Race::COUNTDOWN_DURATION,
+        });
+        game.update();
+        assert!(game.race().is_some_and(|race| race.is_running()));
+
+        for (hero_id, maze_id) in [(first_id, 2), (second_id, 3)] {
+            let hero = game.heros.get_mut(&hero_id).expect("Hero should exist");
+            assert!(matches!(hero.state, HeroState::InMaze { .. }));
+            hero.set_maze_id(maze_id);
+            hero.state = HeroState::Dead {
+                duration: Duration::from_secs(10),
+                instant: Instant::now(),
+            };
+        }
+        game.update();
+
+        let Some(Race::Finished { results }) = game.race() else {
+            panic!("Race should be finished");
+        };
+        assert_eq!(
+            results.iter().map(|result| result.hero_id).collect_vec(),
+            vec![second_id, first_id]
+        );
+    }
+
+    #[test]
+    fn test_game_follows_config() {
+        let mut config = GameConfig {
+            number_of_rooms: 3,
+            ..GameConfig::default()
+        };
+        config.hero.initial_flares = 5;
+        config.minotaurs.count = RoomScaling {
+            base: 2.0,
+            step: 0.0,
+            rooms_per_step: 1,
+            max: 2.0,
+        };
+
+        let mut game = Game::new(config, GameStores::default());
+        assert_eq!(game.number_of_rooms(), 3);
+        for maze_id in 0..3 {
+            assert_eq!(game.minotaurs_in_maze(maze_id), 2);
+        }
+
+        let hero_id = PlayerId::new_v4();
+        game.add_player(hero_id, "hero", false);
+        assert_eq!(
+            game.get_hero(&hero_id).expect("Hero should exist").flares(),
+            5
+        );
+    }
+
+    #[test]
+    fn test_hero_interactions() {
+        // Heros move again right away, without waiting to recover.
+        let config = GameConfig {
+            hero: HeroConfig {
+                initial_speed: 8,
+                min_move_interval_ms: 0,
+                ..HeroConfig::default()
+            },
+            ..GameConfig::default()
+        };
+        let mut game = Game::new_private("123456".to_string(), config);
+        game.set_interactions(HeroInteractions {
+            blocking: true,
+            shoving: true,
+            trading: true,
+        });
+        let (first_id, second_id) = add_two_heros(&mut game);
+
+        // Three free tiles in a row, the heros standing on the first two.
+        let maze = &game.mazes[0];
+        let is_free = |position| {
+            maze.is_walkable_position(position)
+                && maze.is_move_allowed(position, Direction::East)
+                && maze.door_at(position).is_none()
+                && maze.trap_at(position).is_none()
+        };
+        let (x, y) = (0..maze.height)
+            .flat_map(|y| (0..maze.width.saturating_sub(2)).map(move |x| (x, y)))
+            .find(|&(x, y)| is_free((x, y)) && is_free((x + 1, y)) && is_free((x + 2, y)))
+            .expect("Maze should have a corridor");
+        for (hero_id, position) in [(first_id, (x, y)), (second_id, (x + 1, y))] {
+            let hero = game.heros.get_mut(&hero_id).expect("Hero should exist");
+            hero.set_position(position);
+            hero.set_direction(Direction::East);
+            hero.state = HeroState::InMaze {
+                instant: Instant::now(),
+            };
+        }
+
+        game.handle_command(
+            &HeroCommand::Move {
+                direction: Direction::East,
+            },
+            first_id,
+        );
+        let first = game.get_hero(&first_id).expect("Hero should exist");
+        assert_eq!(first.position(), (x, y));
+
+        // Power ups are only given to heros who can still use them.
+        let second = game.heros.get_mut(&second_id).expect("Hero should exist");
+        for power_up in [PowerUp::Speed, PowerUp::Vision] {
+            while second.can_receive_power_up(power_up) {
+                second.receive_power_up(power_up);
+            }
+        }
+        let first = game.heros.get_mut(&first_id).expect("Hero should exist");
+        first.apply_power_up();
+        let power_up = first.held_power_up().expect("Hero should hold a power up");
+        game.handle_command(&HeroCommand::GivePowerUp, first_id);
+        assert_eq!(
+            game.get_hero(&first_id)
+                .expect("Hero should exist")
+                .held_power_up(),
+            (power_up != PowerUp::Memory).then_some(power_up)
+        );
+
+        let third_id = PlayerId::new_v4();
+        game.add_player(third_id, "third", false);
+        let third = game.heros.get_mut(&third_id).expect("Hero should exist");
+        third.set_position((x, y));
+        third.state = HeroState::InMaze {
+            instant: Instant::now(),
+        };
+        game.handle_command(&HeroCommand::GivePowerUp, first_id);
+        assert!(game
+            .get_hero(&first_id)
+            .expect("Hero should exist")
+            .held_power_up()
+            .is_none());
+
+        game.handle_command(&HeroCommand::Shove, first_id);
+        let second = game.get_hero(&second_id).expect("Hero should exist");
+        assert_eq!(second.position(), (x + 2, y));
+    }
+
+    #[test]
+    fn test_coop_revive() {
+        let mut game = Game::new_coop("123456".to_string(), GameConfig::default());
+        let (first_id, second_id) = add_two_heros(&mut game);
+
+        let (x, y) = game
+            .get_hero(&second_id)
+            .expect("Hero should exist")
+            .position();
+        for (hero_id, position) in [(first_id, (x + 1, y)), (second_id, (x, y))] {
+            let hero = game.heros.get_mut(&hero_id).expect("Hero should exist");
+            hero.set_position(position);
+            hero.state = HeroState::InMaze {
+                instant: Instant::now(),
+            };
+        }
+        game.heros
+            .get_mut(&first_id)
+            .expect("Hero should exist")
+            .state = HeroState::Dead {
+            duration: Duration::from_secs(10),
+            instant: Instant::now(),
+        };
+        assert!(game.revive_window(&first_id).is_some());
+
+        // Standing next to the dead hero is not enough, the reviver has to stand on its spot.
+        game.update();
+        assert!(game
+            .get_hero(&first_id)
+            .expect("Hero should exist")
+            .is_dead());
+        game.heros
+            .get_mut(&second_id)
+            .expect("Hero should exist")
+            .set_position((x + 1, y));
+        game.update();
+        let hero = game.get_hero(&first_id).expect("Hero should exist");
+        assert!(matches!(hero.state, HeroState::InMaze { .. }));
+        assert!(hero.elapsed_duration_from_start() >= Duration::from_secs(10));
+        assert_eq!(game.team_depth(), Some((0, 0)));
+    }
+
+    #[test]
+    fn test_reconnect_grace() {
+        let mut game = Game::new_private("123456".to_string(), GameConfig::default());
+        let player_id = PlayerId::new_v4();
+        assert!(!game.detach_player(&player_id));
+
+        game.add_player(player_id, "hero", true);
+        assert!(game.detach_player(&player_id));
+        assert!(game.is_detached(&player_id));
+        assert!(game.reattach_player(&player_id));
+        assert!(!game.reattach_player(&player_id));
+
+        // Heros wait for their player during the grace period, and leave the game after it.
+        game.detach_player(&player_id);
+        game.update();
+        assert!(game.get_hero(&player_id).is_some());
+        game.config.reconnect_grace_secs = 0;
+        game.update();
+        assert!(game.get_hero(&player_id).is_none());
+        assert!(!game.has_detached_players());
+    }
+
+    #[test]
+    fn test_seeded_rooms_and_ghosts() {
+        let mut game = Game::with_seed(42, GameConfig::default(), GameStores::default());
+        let other_game = Game::with_seed(42, GameConfig::default(), GameStores::default());
+        for (maze, other_maze) in game.mazes.iter().zip(other_game.mazes.iter()) {
+            assert_eq!(maze.random_seed(), other_maze.random_seed());
+            assert_eq!(maze.image(), other_maze.image());
+        }
+
+        // Only ghosts of the rooms being played are kept.
+        let seed = game.mazes[0].random_seed();
+        let unused_seed = (0..)
+            .find(|seed| game.mazes.iter().all(|maze| maze.random_seed() != *seed))
+            .expect("Should find an unused seed");
+        let hero_id = PlayerId::new_v4();
+        game.ghosts.insert(
+            hero_id,
+            HashMap::from([(seed, Ghost::default()), (unused_seed, Ghost::default())]),
+        );
+        game.prune_ghosts();
+        assert_eq!(
+            game.ghosts[&hero_id].keys().copied().collect_vec(),
+            vec![seed]
+        );
+    }
 }
@@ -0,0 +1,136 @@
+use super::Position;
+use crate::{backup_json, load_json, save_json, AppResult, PlayerId};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+// Path of a hero through a room, used to race against previous clears.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Ghost {
+    pub duration: Duration,
+    // Unix timestamp of when the room was cleared.
+    pub timestamp: u64,
+    // Positions with the time since the hero entered the room.
+    path: Vec<(Duration, Position)>,
+}
+
+impl Ghost {
+    pub fn record(&mut self, elapsed: Duration, position: Position) {
+        if self.path.last().map(|&(_, last)| last) != Some(position) {
+            self.path.push((elapsed, position));
+        }
+    }
+
+    // The ghost disappears once it has exited the room.
+    pub fn position_at(&self, elapsed: Duration) -> Option<Position> {
+        if elapsed > self.duration {
+            return None;
+        }
+
+        let index = self.path.partition_point(|&(time, _)| time <= elapsed);
+        self.path[..index]
+            .last()
+            .or(self.path.first())
+            .map(|&(_, position)| position)
+    }
+
+    // When the ghost first reached the position, if it ever did.
+    pub fn time_at(&self, position: Position) -> Option<Duration> {
+        self.path
+            .iter()
+            .find(|&&(_, p)| p == position)
+            .map(|&(time, _)| time)
+    }
+}
+
+// Ghost being recorded while a hero explores a room.
+#[derive(Debug, Clone, Default)]
+pub struct GhostRun {
+    pub random_seed: u64,
+    pub ghost: Ghost,
+    // Seconds the hero is behind its best ghost, negative when ahead.
+    pub gap: Option<f64>,
+}
+
+// Best ghosts of registered players, keyed by account id and maze seed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GhostStore {
+    version: u32,
+    pub ghosts: HashMap<PlayerId, HashMap<u64, Ghost>>,
+}
+
+impl Default for GhostStore {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            ghosts: HashMap::new(),
+        }
+    }
+}
+
+impl GhostStore {
+    const VERSION: u32 = 1;
+    const FILENAME: &'static str = "ghosts.json";
+    pub const MAX_GHOSTS_PER_HERO: usize = 50;
+
+    // Only the most recent ghosts of each hero are kept.
+    pub fn new(mut ghosts: HashMap<PlayerId, HashMap<u64, Ghost>>) -> Self {
+        for hero_ghosts in ghosts.values_mut() {
+            if hero_ghosts.len() > Self::MAX_GHOSTS_PER_HERO {
+                let mut timestamps = hero_ghosts
+                    .values()
+                    .map(|ghost| ghost.timestamp)
+                    .collect::<Vec<_>>();
+                timestamps.sort_unstable_by(|a, b| b.cmp(a));
+                let oldest = timestamps[Self::MAX_GHOSTS_PER_HERO - 1];
+                hero_ghosts.retain(|_, ghost| ghost.timestamp >= oldest);
+            }
+        }
+
+        Self {
+            version: Self::VERSION,
+            ghosts,
+        }
+    }
+
+    pub fn load() -> AppResult<Self> {
+        let Some(store) = load_json::<Self>(Self::FILENAME)? else {
+            return Ok(Self::default());
+        };
+
+        if store.version != Self::VERSION {
+            return Err(anyhow!("Unsupported ghosts version {}", store.version));
+        }
+
+        Ok(store)
+    }
+
+    pub fn save(&self) -> AppResult<()> {
+        save_json(Self::FILENAME, self)
+    }
+
+    pub fn backup() -> AppResult<PathBuf> {
+        backup_json(Self::FILENAME)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Ghost;
+    use std::time::Duration;
+
+    #[test]
+    fn test_ghost_replays_recorded_positions() {
+        let mut ghost = Ghost::default();
+        ghost.record(Duration::ZERO, (1, 1));
+        ghost.record(Duration::from_secs(1), (1, 1));
+        ghost.record(Duration::from_secs(2), (2, 1));
+        ghost.duration = Duration::from_secs(3);
+
+        assert_eq!(ghost.position_at(Duration::from_millis(1500)), Some((1, 1)));
+        assert_eq!(ghost.position_at(Duration::from_secs(2)), Some((2, 1)));
+        assert_eq!(ghost.position_at(Duration::from_secs(4)), None);
+        assert_eq!(ghost.time_at((2, 1)), Some(Duration::from_secs(2)));
+        assert_eq!(ghost.time_at((3, 1)), None);
+    }
+}
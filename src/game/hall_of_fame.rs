@@ -0,0 +1,164 @@
+use crate::{backup_json, load_json, now_timestamp, save_json, AppResult, PlayerId};
+use anyhow::anyhow;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, path::PathBuf, time::Duration};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeroRecord {
+    pub id: PlayerId,
+    pub name: String,
+    pub maze_id: usize,
+    pub duration: Duration,
+    // Seconds since the unix epoch at which the record was set.
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MinotaurRecord {
+    pub id: PlayerId,
+    pub name: String,
+    pub maze_id: usize,
+    pub kills: usize,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HallOfFame {
+    version: u32,
+    pub heros: Vec<HeroRecord>,
+    pub minotaurs: Vec<MinotaurRecord>,
+}
+
+impl Default for HallOfFame {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            heros: vec![],
+            minotaurs: vec![],
+        }
+    }
+}
+
+impl HallOfFame {
+    const VERSION: u32 = 1;
+    const FILENAME: &'static str = "hall_of_fame.json";
+    pub const MAX_RECORDS: usize = 1000;
+    pub const MAX_RECORD_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+    pub fn new(heros: Vec<HeroRecord>, minotaurs: Vec<MinotaurRecord>) -> Self {
+        let mut hall_of_fame = Self {
+            version: Self::VERSION,
+            heros,
+            minotaurs,
+        };
+        hall_of_fame.apply_retention();
+        hall_of_fame
+    }
+
+    // Drops records older than MAX_RECORD_AGE and keeps only the best MAX_RECORDS of each kind.
+    fn apply_retention(&mut self) {
+        let oldest_timestamp = now_timestamp().saturating_sub(Self::MAX_RECORD_AGE.as_secs());
+
+        self.heros = self
+            .heros
+            .drain(..)
+            .filter(|record| record.timestamp >= oldest_timestamp)
+            .sorted_by(|a, b| {
+                if b.maze_id == a.maze_id {
+                    a.duration.cmp(&b.duration)
+                } else {
+                    b.maze_id.cmp(&a.maze_id)
+                }
+            })
+            .take(Self::MAX_RECORDS)
+            .collect_vec();
+
+        self.minotaurs = self
+            .minotaurs
+            .drain(..)
+            .filter(|record| record.timestamp >= oldest_timestamp)
+            .sorted_by(|a, b| {
+                if b.kills == a.kills {
+                    b.maze_id.cmp(&a.maze_id)
+                } else {
+                    b.kills.cmp(&a.kills)
+                }
+            })
+            .take(Self::MAX_RECORDS)
+            .collect_vec();
+    }
+
+    pub fn record_ids(&self) -> HashSet<PlayerId> {
+        self.heros
+            .iter()
+            .map(|record| record.id)
+            .chain(self.minotaurs.iter().map(|record| record.id))
+            .collect()
+    }
+
+    pub fn load() -> AppResult<Self> {
+        let Some(hall_of_fame) = load_json::<Self>(Self::FILENAME)? else {
+            return Ok(Self::default());
+        };
+
+        if hall_of_fame.version != Self::VERSION {
+            return Err(anyhow!(
+                "Unsupported hall of fame version {}",
+                hall_of_fame.version
+            ));
+        }
+
+        Ok(Self::new(hall_of_fame.heros, hall_of_fame.minotaurs))
+    }
+
+    pub fn save(&self) -> AppResult<()> {
+        save_json(Self::FILENAME, self)
+    }
+
+    pub fn backup() -> AppResult<PathBuf> {
+        backup_json(Self::FILENAME)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HallOfFame, HeroRecord};
+    use crate::{game::MAX_MAZE_ID, now_timestamp, PlayerId};
+    use rand::Rng;
+    use std::time::Duration;
+
+    #[test]
+    fn test_hall_of_fame_retention() {
+        let rng = &mut rand::thread_rng();
+        let now = now_timestamp();
+
+        let heros = (0..2 * HallOfFame::MAX_RECORDS)
+            .map(|index| HeroRecord {
+                id: PlayerId::new_v4(),
+                name: format!("name#{}", index),
+                maze_id: rng.gen_range(0..=MAX_MAZE_ID),
+                duration: Duration::from_millis(rng.gen_range(15000..150000)),
+                // Make a few records too old to be kept.
+                timestamp: if index % 10 == 0 {
+                    now - HallOfFame::MAX_RECORD_AGE.as_secs() - 1
+                } else {
+                    now
+                },
+            })
+            .collect();
+
+        let hall_of_fame = HallOfFame::new(heros, vec![]);
+        assert_eq!(hall_of_fame.heros.len(), HallOfFame::MAX_RECORDS);
+        assert!(hall_of_fame
+            .heros
+            .iter()
+            .all(|record| record.timestamp == now));
+        for records in hall_of_fame.heros.windows(2) {
+            assert!(
+                records[0].maze_id > records[1].maze_id
+                    || records[0].duration <= records[1].duration
+            );
+        }
+    }
+}
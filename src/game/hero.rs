@@ -1,18 +1,29 @@
-use super::{entity::Entity, power_up::PowerUp, Direction, Position};
+use super::{
+    chat::PingKind, config::HeroConfig, door::KeyColor, entity::Entity, power_up::PowerUp,
+    Direction, Position,
+};
 use crate::PlayerId;
 use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
     time::{Duration, Instant},
 };
 use strum_macros::Display;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum HeroCommand {
     Move { direction: Direction },
     TurnClockwise,
     TurnCounterClockwise,
     CycleUiOptions,
+    DropFlare,
+    DropMark,
+    EraseMark,
+    ToggleShareMarks,
+    Ping { kind: PingKind },
+    Shove,
+    GivePowerUp,
 }
 
 impl HeroCommand {
@@ -22,6 +33,18 @@ impl HeroCommand {
                 'a' => Some(Self::TurnCounterClockwise),
                 'd' => Some(Self::TurnClockwise),
                 'w' => Some(Self::CycleUiOptions),
+                'f' => Some(Self::DropFlare),
+                'm' => Some(Self::DropMark),
+                'x' => Some(Self::EraseMark),
+                'c' => Some(Self::ToggleShareMarks),
+                'p' => Some(Self::Shove),
+                'g' => Some(Self::GivePowerUp),
+                '1' => Some(Self::Ping {
+                    kind: PingKind::MinotaurHere,
+                }),
+                '2' => Some(Self::Ping {
+                    kind: PingKind::ExitThisWay,
+                }),
 
                 'h' => Some(Self::Move {
                     direction: Direction::West,
@@ -97,17 +120,26 @@ pub struct Hero {
     memory: u64,
     past_visible_positions: HashMap<usize, HashMap<Position, Instant>>,
     last_move_time: Instant,
+    // Run duration at which the hero entered the current maze.
+    maze_entered_at: Duration,
+    slowed_until: Option<Instant>,
     collected_power_ups: HashMap<usize, PowerUp>,
+    // Rooms whose power up was handed to another hero.
+    given_power_ups: HashSet<usize>,
+    held_keys: HashMap<usize, Vec<KeyColor>>,
+    flares: usize,
+    marks: HashMap<usize, Vec<Position>>,
+    pub share_marks: bool,
     pub ui_options: UiOptions,
+    config: HeroConfig,
 }
 
 impl Hero {
-    pub const MAX_SPEED: u64 = 8;
-    pub const MAX_VISION: usize = 8;
-    pub const INITIAL_SPEED: u64 = 4;
-    pub const INITIAL_VISION: usize = 1;
-    pub const INITIAL_MEMORY: u64 = 0;
-    pub fn new(id: PlayerId, name: String, position: Position) -> Self {
+    pub const MAX_MARKS_PER_MAZE: usize = 10;
+    pub const MARK_GLYPH: char = '×';
+    pub const SHARED_MARK_GLYPH: char = '+';
+    const SLOWED_RECOVERY_FACTOR: u32 = 3;
+    pub fn new(id: PlayerId, name: String, position: Position, config: HeroConfig) -> Self {
         let state = HeroState::WaitingToStart;
         Self {
             id,
@@ -116,13 +148,21 @@ impl Hero {
             maze_id: 0,
             position,
             direction: Direction::East,
-            vision: Self::INITIAL_VISION,
-            speed: Self::INITIAL_SPEED,
-            memory: Self::INITIAL_MEMORY,
+            vision: config.initial_vision,
+            speed: config.initial_speed,
+            memory: config.initial_memory,
             past_visible_positions: HashMap::new(),
             last_move_time: Instant::now(),
+            maze_entered_at: Duration::ZERO,
+            slowed_until: None,
             collected_power_ups: HashMap::new(),
+            given_power_ups: HashSet::new(),
+            held_keys: HashMap::new(),
+            flares: config.initial_flares,
+            marks: HashMap::new(),
+            share_marks: false,
             ui_options: UiOptions::Dark,
+            config,
         }
     }
 
@@ -131,12 +171,18 @@ impl Hero {
         self.maze_id = 0;
         self.position = position;
         self.direction = Direction::East;
-        self.vision = Self::INITIAL_VISION;
-        self.speed = Self::INITIAL_SPEED;
-        self.memory = Self::INITIAL_MEMORY;
+        self.vision = self.config.initial_vision;
+        self.speed = self.config.initial_speed;
+        self.memory = self.config.initial_memory;
         self.past_visible_positions.clear();
         self.last_move_time = Instant::now();
+        self.maze_entered_at = Duration::ZERO;
+        self.slowed_until = None;
         self.collected_power_ups.clear();
+        self.given_power_ups.clear();
+        self.held_keys.clear();
+        self.flares = self.config.initial_flares;
+        self.marks.clear();
     }
 
     pub fn is_dead(&self) -> bool {
@@ -164,7 +210,27 @@ impl Hero {
             return false;
         }
 
-        self.last_move_time.elapsed() >= self.movement_recovery_duration()
+        let recovery_duration = if self.is_slowed() {
+            self.movement_recovery_duration() * Self::SLOWED_RECOVERY_FACTOR
+        } else {
+            self.movement_recovery_duration()
+        };
+
+        self.last_move_time.elapsed() >= recovery_duration
+    }
+
+    // Actions other than moving, such as shoving, also wait for the hero to recover.
+    pub fn spend_move(&mut self) {
+        self.last_move_time = Instant::now();
+    }
+
+    pub fn is_slowed(&self) -> bool {
+        self.slowed_until
+            .is_some_and(|instant| instant > Instant::now())
+    }
+
+    pub fn slow_down(&mut self, duration: Duration) {
+        self.slowed_until = Some(Instant::now() + duration);
     }
 
     pub fn past_visibility_duration(&self) -> Duration {
@@ -198,8 +264,27 @@ impl Hero {
         self.last_move_time = Instant::now();
     }
 
+    pub fn set_vision(&mut self, vision: usize) {
+        self.vision = vision;
+    }
+
     pub fn set_maze_id(&mut self, maze_id: usize) {
         self.maze_id = maze_id;
+        self.maze_entered_at = self.elapsed_duration_from_start();
+    }
+
+    // Time since the start of the run at which the hero entered its current room.
+    pub fn maze_entered_at(&self) -> Duration {
+        self.maze_entered_at
+    }
+
+    pub fn elapsed_duration_in_maze(&self) -> Duration {
+        self.elapsed_duration_from_start()
+            .saturating_sub(self.maze_entered_at)
+    }
+
+    pub fn max_vision(&self) -> usize {
+        self.config.max_vision
     }
 
     pub fn decrease_vision(&mut self) {
@@ -209,11 +294,11 @@ impl Hero {
     pub fn apply_power_up(&mut self) {
         let mut available_power_ups = vec![];
 
-        if self.speed < Self::MAX_SPEED {
+        if self.speed < self.config.max_speed {
             available_power_ups.push(PowerUp::Speed);
         }
 
-        if self.vision < Self::MAX_VISION {
+        if self.vision < self.config.max_vision {
             available_power_ups.push(PowerUp::Vision);
         }
 
@@ -222,18 +307,116 @@ impl Hero {
         let idx = rand::random::<usize>() % available_power_ups.len();
 
         let power_up = available_power_ups[idx];
+        self.receive_power_up(power_up);
+        self.collected_power_ups.insert(self.maze_id, power_up);
+    }
+
+    // Speed and vision are capped, so they are only worth receiving below their maximum.
+    pub fn can_receive_power_up(&self, power_up: PowerUp) -> bool {
+        match power_up {
+            PowerUp::Speed => self.speed < self.config.max_speed,
+            PowerUp::Vision => self.vision < self.config.max_vision,
+            PowerUp::Memory => true,
+        }
+    }
+
+    pub fn receive_power_up(&mut self, power_up: PowerUp) {
         match power_up {
-            PowerUp::Speed => self.speed = (self.speed + 1).min(Self::MAX_SPEED),
-            PowerUp::Vision => self.vision = (self.vision + 1).min(Self::MAX_VISION),
+            PowerUp::Speed => self.speed = (self.speed + 1).min(self.config.max_speed),
+            PowerUp::Vision => self.vision = (self.vision + 1).min(self.config.max_vision),
             PowerUp::Memory => self.memory += 1,
         }
+    }
 
-        self.collected_power_ups.insert(self.maze_id, power_up);
+    // The power up collected in the current room, unless it was given away.
+    pub fn held_power_up(&self) -> Option<PowerUp> {
+        if self.given_power_ups.contains(&self.maze_id) {
+            return None;
+        }
+        self.power_up_collected_in_maze().copied()
+    }
+
+    // Loses the effect of the held power up, so that it can be handed over.
+    pub fn give_power_up(&mut self) -> Option<PowerUp> {
+        let power_up = self.held_power_up()?;
+        match power_up {
+            PowerUp::Speed => self.speed = self.speed.saturating_sub(1),
+            PowerUp::Vision => self.vision = self.vision.saturating_sub(1),
+            PowerUp::Memory => self.memory = self.memory.saturating_sub(1),
+        }
+        self.given_power_ups.insert(self.maze_id);
+        Some(power_up)
+    }
+
+    pub fn power_ups_collected_in_run(&self) -> impl Iterator<Item = &PowerUp> {
+        self.collected_power_ups.values()
     }
 
     pub fn power_up_collected_in_maze(&self) -> Option<&PowerUp> {
         self.collected_power_ups.get(&self.maze_id)
     }
+
+    pub fn flares(&self) -> usize {
+        self.flares
+    }
+
+    pub fn use_flare(&mut self) -> bool {
+        if self.flares == 0 {
+            return false;
+        }
+        self.flares -= 1;
+        true
+    }
+
+    pub fn drop_mark(&mut self) -> bool {
+        let position = self.position;
+        let marks = self.marks.entry(self.maze_id).or_default();
+        if marks.len() >= Self::MAX_MARKS_PER_MAZE || marks.contains(&position) {
+            return false;
+        }
+        marks.push(position);
+        true
+    }
+
+    // Erases the mark on the current position, or the last dropped one if there is none.
+    pub fn erase_mark(&mut self) -> bool {
+        let position = self.position;
+        let Some(marks) = self.marks.get_mut(&self.maze_id) else {
+            return false;
+        };
+
+        if let Some(index) = marks.iter().position(|&mark| mark == position) {
+            marks.remove(index);
+            return true;
+        }
+
+        marks.pop().is_some()
+    }
+
+    pub fn marks_in_maze(&self) -> &[Position] {
+        self.marks
+            .get(&self.maze_id)
+            .map(|marks| marks.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn pick_up_key(&mut self, color: KeyColor) {
+        let held_keys = self.held_keys.entry(self.maze_id).or_default();
+        if !held_keys.contains(&color) {
+            held_keys.push(color);
+        }
+    }
+
+    pub fn has_key(&self, color: KeyColor) -> bool {
+        self.held_keys_in_maze().contains(&color)
+    }
+
+    pub fn held_keys_in_maze(&self) -> &[KeyColor] {
+        self.held_keys
+            .get(&self.maze_id)
+            .map(|keys| keys.as_slice())
+            .unwrap_or_default()
+    }
 }
 
 impl Entity for Hero {
@@ -253,6 +436,12 @@ impl Entity for Hero {
         self.speed
     }
 
+    fn movement_recovery_duration(&self) -> Duration {
+        Duration::from_millis(
+            self.config.min_move_interval_ms + 200_u64.saturating_sub(25 * self.speed),
+        )
+    }
+
     fn position(&self) -> super::Position {
         self.position
     }
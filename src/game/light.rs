@@ -0,0 +1,66 @@
+use super::{IntoDirection, Position};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightSourceKind {
+    Torch,
+    Flare { instant: Instant },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightSource {
+    pub position: Position,
+    pub radius: usize,
+    pub kind: LightSourceKind,
+}
+
+impl LightSource {
+    pub const TORCH_RADIUS: usize = 8;
+    pub const FLARE_RADIUS: usize = 10;
+    pub const FLARE_DURATION: Duration = Duration::from_secs(20);
+    // Positions closer than this are always visible, even in complete darkness.
+    pub const DARK_VISION_RADIUS: f64 = 2.5;
+    // Lowest intensity used when drawing, so that positions seen in the dark are not black.
+    pub const MIN_DRAWN_INTENSITY: f64 = 0.3;
+
+    pub fn torch(position: Position) -> Self {
+        Self {
+            position,
+            radius: Self::TORCH_RADIUS,
+            kind: LightSourceKind::Torch,
+        }
+    }
+
+    pub fn flare(position: Position) -> Self {
+        Self {
+            position,
+            radius: Self::FLARE_RADIUS,
+            kind: LightSourceKind::Flare {
+                instant: Instant::now(),
+            },
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        match self.kind {
+            LightSourceKind::Torch => true,
+            LightSourceKind::Flare { instant } => instant.elapsed() < Self::FLARE_DURATION,
+        }
+    }
+
+    pub fn intensity_at(&self, position: Position) -> f64 {
+        if !self.is_active() {
+            return 0.0;
+        }
+
+        let intensity = (1.0 - self.position.distance(position) / self.radius as f64).max(0.0);
+        match self.kind {
+            LightSourceKind::Torch => intensity,
+            // Flares fade out as they burn.
+            LightSourceKind::Flare { instant } => {
+                intensity
+                    * (1.0 - instant.elapsed().as_secs_f64() / Self::FLARE_DURATION.as_secs_f64())
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use super::{
-    direction::Direction, minotaur::Minotaur, Entity, IntoDirection, Position, View, MAX_MAZE_ID,
+    direction::Direction, door::KeyColor, light::LightSource, minotaur::Minotaur, trap::Trap,
+    Entity, IntoDirection, MinotaurConfig, Position, View, MAX_MAZE_ID,
 };
 use crate::AppResult;
 use image::{Rgba, RgbaImage};
@@ -9,9 +10,11 @@ use rand::{
     Rng, SeedableRng,
 };
 use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
     path::Path,
+    time::Instant,
 };
 use strum::IntoEnumIterator;
 use strum_macros::{Display, EnumIter};
@@ -55,19 +58,19 @@ impl Cell {
     }
 }
 
-#[derive(Debug, Clone, Copy, Display, EnumIter, PartialEq)]
+#[derive(Debug, Clone, Copy, Display, EnumIter, PartialEq, Serialize, Deserialize)]
 pub enum MazeGenerationAlgorithm {
     DepthFirstSearch,
     Wilson,
 }
 
-#[derive(Debug, Clone, Copy, Display, EnumIter, PartialEq)]
+#[derive(Debug, Clone, Copy, Display, EnumIter, PartialEq, Serialize, Deserialize)]
 pub enum MazeImageStyle {
     Straight,
     Seasaw,
 }
 
-#[derive(Debug, Clone, Copy, Display, EnumIter, PartialEq)]
+#[derive(Debug, Clone, Copy, Display, EnumIter, PartialEq, Serialize, Deserialize)]
 pub enum MazeTopology {
     Orthogonal,
     Chessboard,
@@ -88,6 +91,20 @@ impl MazeTopology {
     }
 }
 
+// Everything needed to generate the same maze again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MazeDescriptor {
+    pub id: usize,
+    pub random_seed: u64,
+    pub width: usize,
+    pub height: usize,
+    pub generation_algorithm: MazeGenerationAlgorithm,
+    pub topology: MazeTopology,
+    pub image_style: MazeImageStyle,
+    // The power up position is not derived from the seed.
+    pub power_up_position: Option<Position>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Maze {
     pub id: usize,
@@ -102,6 +119,14 @@ pub struct Maze {
     entrance: Vec<Position>,
     exit: Vec<Position>,
     pub power_up_position: Option<Position>,
+    traps: HashMap<Position, Trap>,
+    collapsed_positions: HashMap<Position, Instant>,
+    doors: HashMap<Position, KeyColor>,
+    keys: HashMap<KeyColor, Position>,
+    teleporters: HashMap<Position, Position>,
+    one_way_gates: HashMap<Position, Direction>,
+    ambient_light: f64,
+    light_sources: Vec<LightSource>,
     visible_positions_cache: HashMap<(usize, usize, Direction, View), HashSet<Position>>, // (x, y, direction, type) -> visible positions
     success_rate: (usize, usize), //pass/attempted
 }
@@ -109,6 +134,11 @@ pub struct Maze {
 impl Maze {
     const CELL_SIZE: usize = 6;
     const WALL_SIZE: usize = 2;
+    // Traps use their own rng stream, so that adding them does not change the maze layout.
+    const TRAP_RNG_STREAM: u64 = 1;
+    const DOOR_RNG_STREAM: u64 = 2;
+    const PASSAGE_RNG_STREAM: u64 = 3;
+    const LIGHT_RNG_STREAM: u64 = 4;
 
     fn cell_image_position(&self, position: Position) -> Position {
         // The cell position indicate the top-left corner.
@@ -410,11 +440,366 @@ impl Maze {
                     .iter()
                     .all(|entrance| entrance.distance(position) > 6.0)
                     && self.exit.iter().all(|exit| exit.distance(position) > 6.0)
+                    && !self.traps.contains_key(&position)
+                    && !self.doors.contains_key(&position)
+                    && !self.keys.values().contains(&position)
+                    && !self.teleporters.contains_key(&position)
+                    && !self.one_way_gates.contains_key(&position)
             })
             .choose(&mut rand::thread_rng())
             .copied()
     }
 
+    // Traps cover a whole character of the rendered image (two vertically stacked positions),
+    // so that their glyph is unambiguous.
+    fn generate_traps(&self) -> HashMap<Position, Trap> {
+        let mut traps = HashMap::new();
+        // The first room is a safe place to get used to the controls.
+        if self.id == 0 {
+            return traps;
+        }
+
+        let rng = &mut ChaCha8Rng::seed_from_u64(self.random_seed);
+        rng.set_stream(Self::TRAP_RNG_STREAM);
+
+        // Positions are sorted so that the placement only depends on the seed.
+        let candidates = self
+            .valid_positions
+            .iter()
+            .filter(|&&(x, y)| {
+                y % 2 == 0
+                    && self.is_valid_position((x, y + 1))
+                    && self
+                        .entrance
+                        .iter()
+                        .all(|entrance| entrance.distance((x, y)) > 6.0)
+                    && self.exit.iter().all(|exit| exit.distance((x, y)) > 6.0)
+            })
+            .copied()
+            .sorted()
+            .collect_vec();
+
+        let available_traps = Trap::iter().collect_vec();
+        let number_of_traps = rng.gen_range(self.id..=2 * self.id).min(candidates.len());
+        for &(x, y) in candidates.choose_multiple(rng, number_of_traps) {
+            let trap = *available_traps.choose(rng).unwrap();
+            traps.insert((x, y), trap);
+            traps.insert((x, y + 1), trap);
+
+            // Spike pits must never cut the way to the exit.
+            if trap == Trap::SpikePit {
+                let reachable_positions = self.reachable_positions(self.entrance[0], |position| {
+                    traps.get(&position) == Some(&Trap::SpikePit)
+                });
+                if !self
+                    .exit
+                    .iter()
+                    .any(|exit| reachable_positions.contains(exit))
+                {
+                    traps.remove(&(x, y));
+                    traps.remove(&(x, y + 1));
+                }
+            }
+        }
+
+        traps
+    }
+
+    // Doors are placed across horizontal corridors, where they cover a whole character
+    // of the rendered image. The first lock can be on the exit itself.
+    // Keys are placed where they can be reached from the entrance with every door closed,
+    // so a room can always be solved.
+    fn generate_doors_and_keys(
+        &self,
+    ) -> (HashMap<Position, KeyColor>, HashMap<KeyColor, Position>) {
+        let mut doors = HashMap::new();
+        let mut keys = HashMap::new();
+        if self.id < 2 {
+            return (doors, keys);
+        }
+
+        let rng = &mut ChaCha8Rng::seed_from_u64(self.random_seed);
+        rng.set_stream(Self::DOOR_RNG_STREAM);
+
+        let is_far_from_entrance_and_exit = |position: Position| {
+            self.entrance
+                .iter()
+                .all(|entrance| entrance.distance(position) > 6.0)
+                && self.exit.iter().all(|exit| exit.distance(position) > 6.0)
+        };
+
+        let door_candidates = self
+            .valid_positions
+            .iter()
+            .filter(|&&(x, y)| {
+                y % 2 == 0
+                    && y > 0
+                    && self.is_valid_position((x, y + 1))
+                    && !self.is_valid_position((x, y - 1))
+                    && !self.is_valid_position((x, y + 2))
+                    && !self.traps.contains_key(&(x, y))
+                    && !self.traps.contains_key(&(x, y + 1))
+                    && is_far_from_entrance_and_exit((x, y))
+            })
+            .copied()
+            .sorted()
+            .collect_vec();
+
+        let colors = KeyColor::iter().collect_vec();
+        let number_of_locks = rng.gen_range(1..=(1 + self.id / 4).min(colors.len()));
+        let lock_exit = rng.gen_bool(0.5);
+        let mut used_colors = vec![];
+        for &color in colors.iter().take(number_of_locks) {
+            if used_colors.is_empty() && lock_exit {
+                for &position in self.exit.iter() {
+                    doors.insert(position, color);
+                }
+            } else if let Some(&(x, y)) = door_candidates.choose(rng) {
+                doors.insert((x, y), color);
+                doors.insert((x, y + 1), color);
+            } else {
+                continue;
+            }
+            used_colors.push(color);
+        }
+
+        let reachable_positions = self.reachable_positions(self.entrance[0], |position| {
+            doors.contains_key(&position) || self.traps.get(&position) == Some(&Trap::SpikePit)
+        });
+
+        let key_candidates = reachable_positions
+            .iter()
+            .filter(|&&position| {
+                !self.traps.contains_key(&position)
+                    && self
+                        .entrance
+                        .iter()
+                        .all(|entrance| entrance.distance(position) > 6.0)
+            })
+            .copied()
+            .sorted()
+            .collect_vec();
+
+        for (&color, &position) in used_colors
+            .iter()
+            .zip(key_candidates.choose_multiple(rng, used_colors.len()))
+        {
+            keys.insert(color, position);
+        }
+
+        // Never leave a door without its key.
+        doors.retain(|_, color| keys.contains_key(color));
+
+        (doors, keys)
+    }
+
+    // Teleporter pads and one-way gates cover a whole character of the rendered image,
+    // like traps. Gates are only placed along the shortest path to the exit, pointing
+    // toward it, so they can never cut the exit off. Rooms with doors get no gates,
+    // to avoid leaving a key behind a gate.
+    fn generate_teleporters_and_gates(
+        &self,
+    ) -> (HashMap<Position, Position>, HashMap<Position, Direction>) {
+        let mut teleporters = HashMap::new();
+        let mut one_way_gates = HashMap::new();
+        if self.id < 2 {
+            return (teleporters, one_way_gates);
+        }
+
+        let rng = &mut ChaCha8Rng::seed_from_u64(self.random_seed);
+        rng.set_stream(Self::PASSAGE_RNG_STREAM);
+
+        let is_free = |(x, y): Position| {
+            [(x, y), (x, y + 1)].iter().all(|&position| {
+                self.is_valid_position(position)
+                    && !self.traps.contains_key(&position)
+                    && !self.doors.contains_key(&position)
+                    && !self.keys.values().contains(&position)
+            }) && self
+                .entrance
+                .iter()
+                .all(|entrance| entrance.distance((x, y)) > 6.0)
+                && self.exit.iter().all(|exit| exit.distance((x, y)) > 6.0)
+        };
+
+        let pad_candidates = self
+            .valid_positions
+            .iter()
+            .filter(|&&(x, y)| y % 2 == 0 && is_free((x, y)))
+            .copied()
+            .sorted()
+            .collect_vec();
+
+        let number_of_teleporters = rng.gen_range(0..=1 + self.id / 5);
+        for _ in 0..number_of_teleporters {
+            let (Some(&from), Some(&to)) = (pad_candidates.choose(rng), pad_candidates.choose(rng))
+            else {
+                break;
+            };
+
+            let is_taken = |(x, y): Position| {
+                teleporters.contains_key(&(x, y)) || teleporters.contains_key(&(x, y + 1))
+            };
+            if from.distance(to) < 20.0 || is_taken(from) || is_taken(to) {
+                continue;
+            }
+
+            for dy in 0..2 {
+                teleporters.insert((from.0, from.1 + dy), (to.0, to.1 + dy));
+                teleporters.insert((to.0, to.1 + dy), (from.0, from.1 + dy));
+            }
+        }
+
+        if !self.doors.is_empty() {
+            return (teleporters, one_way_gates);
+        }
+
+        let Some(path) = self.shortest_path(self.entrance[0], &self.exit, |_| false) else {
+            return (teleporters, one_way_gates);
+        };
+
+        // Gates span horizontal corridors, where the path moves east or west.
+        let gate_candidates = path
+            .iter()
+            .tuple_windows()
+            .filter_map(|(&previous, &(x, y), &next)| {
+                let y = y / 2 * 2;
+                let is_straight = previous.1 == next.1 && previous.0.abs_diff(next.0) == 2;
+                let is_corridor = y > 0
+                    && !self.is_valid_position((x, y - 1))
+                    && !self.is_valid_position((x, y + 2));
+                let is_teleporter =
+                    teleporters.contains_key(&(x, y)) || teleporters.contains_key(&(x, y + 1));
+                if !is_straight || !is_corridor || is_teleporter || !is_free((x, y)) {
+                    return None;
+                }
+
+                let direction = if next.0 > previous.0 {
+                    Direction::East
+                } else {
+                    Direction::West
+                };
+                Some(((x, y), direction))
+            })
+            .collect_vec();
+
+        let number_of_gates = rng.gen_range(1..=2);
+        for &((x, y), direction) in gate_candidates.choose_multiple(rng, number_of_gates) {
+            one_way_gates.insert((x, y), direction);
+            one_way_gates.insert((x, y + 1), direction);
+        }
+
+        (teleporters, one_way_gates)
+    }
+
+    // Torches hang on walls facing a corridor.
+    fn generate_torches(&self) -> Vec<LightSource> {
+        if self.ambient_light >= 1.0 {
+            return vec![];
+        }
+
+        let rng = &mut ChaCha8Rng::seed_from_u64(self.random_seed);
+        rng.set_stream(Self::LIGHT_RNG_STREAM);
+
+        let candidates = self
+            .valid_positions
+            .iter()
+            .flat_map(|position| {
+                [
+                    Direction::North,
+                    Direction::East,
+                    Direction::South,
+                    Direction::West,
+                ]
+                .map(|direction| position.into_direction(&direction))
+            })
+            .filter(|&position| {
+                !self.is_valid_position(position)
+                    && position.0 < self.image.width() as usize
+                    && position.1 < self.image.height() as usize
+            })
+            .unique()
+            .sorted()
+            .collect_vec();
+
+        candidates
+            .choose_multiple(rng, 1 + self.id / 2)
+            .map(|&position| LightSource::torch(position))
+            .collect_vec()
+    }
+
+    // Breadth-first search from the starting position, following teleporters and
+    // respecting one-way gates. Maps each reached position to the one it was reached from.
+    fn breadth_first_search<F>(&self, from: Position, is_blocked: F) -> HashMap<Position, Position>
+    where
+        F: Fn(Position) -> bool,
+    {
+        let mut parents = HashMap::from([(from, from)]);
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(position) = queue.pop_front() {
+            for direction in [
+                Direction::North,
+                Direction::East,
+                Direction::South,
+                Direction::West,
+            ] {
+                let mut next = position.into_direction(&direction);
+                if !self.is_valid_position(next)
+                    || is_blocked(next)
+                    || !self.is_move_allowed(position, direction)
+                {
+                    continue;
+                }
+
+                if let Some(&destination) = self.teleporters.get(&next) {
+                    next = destination;
+                }
+
+                if let Entry::Vacant(entry) = parents.entry(next) {
+                    entry.insert(position);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        parents
+    }
+
+    // Returns all the positions that can be reached from the starting position
+    // without stepping on a blocked position.
+    pub fn reachable_positions<F>(&self, from: Position, is_blocked: F) -> HashSet<Position>
+    where
+        F: Fn(Position) -> bool,
+    {
+        self.breadth_first_search(from, is_blocked)
+            .into_keys()
+            .collect()
+    }
+
+    // Returns the shortest path from the starting position to the closest target, both included.
+    pub fn shortest_path<F>(
+        &self,
+        from: Position,
+        to: &[Position],
+        is_blocked: F,
+    ) -> Option<Vec<Position>>
+    where
+        F: Fn(Position) -> bool,
+    {
+        let parents = self.breadth_first_search(from, is_blocked);
+        let mut position = *to.iter().find(|target| parents.contains_key(target))?;
+
+        let mut path = vec![position];
+        while position != from {
+            position = parents[&position];
+            path.push(position);
+        }
+        path.reverse();
+
+        Some(path)
+    }
+
     fn color(id: usize) -> Rgba<u8> {
         let a = (id.min(MAX_MAZE_ID) as f64) / MAX_MAZE_ID as f64;
         // red = Rgba([208, 28, 28, 125]);
@@ -444,7 +829,10 @@ impl Maze {
     }
 
     pub fn random(id: usize) -> Self {
-        let random_seed = ChaCha8Rng::from_entropy().gen();
+        Self::from_seed(id, ChaCha8Rng::from_entropy().gen())
+    }
+
+    pub fn from_seed(id: usize, random_seed: u64) -> Self {
         let rng = &mut ChaCha8Rng::seed_from_u64(random_seed);
         let width = rng.gen_range(10 + 2 * (id / 4)..=(12 + 2 * (id / 2)).min(32));
         let height = rng.gen_range(4 + 2 * (id / 4)..=(6 + 2 * (id / 2)).min(20));
@@ -498,40 +886,122 @@ impl Maze {
             entrance: Vec::new(),
             exit: Vec::new(),
             power_up_position: None,
+            traps: HashMap::new(),
+            collapsed_positions: HashMap::new(),
+            doors: HashMap::new(),
+            keys: HashMap::new(),
+            teleporters: HashMap::new(),
+            one_way_gates: HashMap::new(),
+            ambient_light: (1.0 - 1.25 * id as f64 / MAX_MAZE_ID as f64).max(0.0),
+            light_sources: vec![],
             visible_positions_cache: HashMap::new(),
             success_rate: (0, 0),
         };
 
         let cells = maze.generate_cells();
         maze.generate_maze(cells, entrance, exit);
+        maze.traps = maze.generate_traps();
+        (maze.doors, maze.keys) = maze.generate_doors_and_keys();
+        (maze.teleporters, maze.one_way_gates) = maze.generate_teleporters_and_gates();
+        maze.light_sources = maze.generate_torches();
         maze.power_up_position = maze.random_valid_power_up_position();
         maze.generate_image();
 
-        println!("Generated maze {}", maze.random_seed);
+        log::debug!("Generated maze {}", maze.random_seed);
+
+        maze
+    }
+
+    pub fn random_seed(&self) -> u64 {
+        self.random_seed
+    }
+
+    pub fn descriptor(&self) -> MazeDescriptor {
+        MazeDescriptor {
+            id: self.id,
+            random_seed: self.random_seed,
+            width: self.width,
+            height: self.height,
+            generation_algorithm: self.generation_algorithm,
+            topology: self.topology,
+            image_style: self.image_style,
+            power_up_position: self.power_up_position,
+        }
+    }
 
+    pub fn from_descriptor(descriptor: &MazeDescriptor) -> Self {
+        let mut maze = Self::new(
+            descriptor.id,
+            descriptor.random_seed,
+            descriptor.width,
+            descriptor.height,
+            None,
+            None,
+            descriptor.generation_algorithm,
+            descriptor.topology,
+            descriptor.image_style,
+        );
+        maze.power_up_position = descriptor.power_up_position;
         maze
     }
 
-    pub fn spawn_minotaur(&mut self, name: String) -> Minotaur {
+    pub fn spawn_minotaur(&mut self, name: String, config: &MinotaurConfig) -> Minotaur {
         let mut position = self.random_valid_position();
         while position.distance(self.entrance[0]) < 10.0 {
             position = self.random_valid_position()
         }
 
-        let speed = (self.id as u64 / 3).min(6);
-        let aggression = (0.5 + 0.1 * (self.id / 2) as f64).min(1.0);
-        let vision = (4 + self.id / 3).min(7);
+        let speed = config.speed.value(self.id) as u64;
+        let aggression = config.aggression.value(self.id);
+        let vision = config.vision.value(self.id) as usize;
         let minotaur = Minotaur::new(name, self.id, position, speed, vision, aggression);
         self.get_and_cache_visible_positions(position, minotaur.direction(), minotaur.view());
 
         minotaur
     }
 
+    // Returns the positions in view which are bright enough to be seen from the given position.
+    // Lit positions are visible up to the view radius, unlit ones only up to a small radius.
+    fn apply_lighting(
+        &self,
+        position: Position,
+        view: View,
+        visible_positions: HashSet<Position>,
+    ) -> HashSet<Position> {
+        if self.ambient_light >= 1.0 {
+            return visible_positions;
+        }
+
+        let view_radius = view.radius() as f64;
+        visible_positions
+            .into_iter()
+            .filter(|&visible_position| {
+                let distance = position.distance(visible_position);
+                distance <= LightSource::DARK_VISION_RADIUS
+                    || distance
+                        <= LightSource::DARK_VISION_RADIUS
+                            + (view_radius - LightSource::DARK_VISION_RADIUS)
+                                * self.light_level(visible_position)
+            })
+            .collect()
+    }
+
     pub fn get_and_cache_visible_positions(
         &mut self,
         position: Position,
         direction: Direction,
         view: View,
+    ) -> HashSet<Position> {
+        let line_of_sight_positions =
+            self.get_and_cache_line_of_sight_positions(position, direction, view);
+        self.apply_lighting(position, view, line_of_sight_positions)
+    }
+
+    fn get_and_cache_line_of_sight_positions(
+        &mut self,
+        position: Position,
+        direction: Direction,
+        view: View,
     ) -> HashSet<Position> {
         let cache_key = (position.0, position.1, direction, view);
         if let Some(visible_positions) = self.visible_positions_cache.get(&cache_key) {
@@ -722,10 +1192,29 @@ impl Maze {
         view: View,
     ) -> HashSet<Position> {
         let cache_key = (position.0, position.1, direction, view);
-        self.visible_positions_cache
+        let line_of_sight_positions = self
+            .visible_positions_cache
             .get(&cache_key)
             .expect("Visible positions should have been cached")
-            .clone()
+            .clone();
+        self.apply_lighting(position, view, line_of_sight_positions)
+    }
+
+    pub fn light_level(&self, position: Position) -> f64 {
+        self.light_sources
+            .iter()
+            .fold(self.ambient_light, |light, source| {
+                light + source.intensity_at(position)
+            })
+            .min(1.0)
+    }
+
+    pub fn light_sources(&self) -> &Vec<LightSource> {
+        &self.light_sources
+    }
+
+    pub fn drop_flare(&mut self, position: Position) {
+        self.light_sources.push(LightSource::flare(position));
     }
 
     pub fn image(&self) -> &RgbaImage {
@@ -767,10 +1256,134 @@ impl Maze {
 
     pub fn is_valid_minotaur_position(&self, position: Position) -> bool {
         let entrances = self.entrance_positions();
-        self.valid_positions.get(&position).is_some()
+        self.is_walkable_position(position)
+            && !self.doors.contains_key(&position)
             && entrances.iter().all(|p| p.distance(position) > 6.0)
     }
 
+    pub fn door_at(&self, position: Position) -> Option<KeyColor> {
+        self.doors.get(&position).copied()
+    }
+
+    pub fn doors(&self) -> &HashMap<Position, KeyColor> {
+        &self.doors
+    }
+
+    pub fn key_at(&self, position: Position) -> Option<KeyColor> {
+        self.keys
+            .iter()
+            .find(|(_, &key_position)| key_position == position)
+            .map(|(&color, _)| color)
+    }
+
+    pub fn keys(&self) -> &HashMap<KeyColor, Position> {
+        &self.keys
+    }
+
+    pub fn teleporter_destination(&self, position: Position) -> Option<Position> {
+        self.teleporters.get(&position).copied()
+    }
+
+    pub fn teleporters(&self) -> &HashMap<Position, Position> {
+        &self.teleporters
+    }
+
+    pub fn one_way_gates(&self) -> &HashMap<Position, Direction> {
+        &self.one_way_gates
+    }
+
+    // One-way gates can only be entered and crossed in their direction.
+    pub fn is_move_allowed(&self, from: Position, direction: Direction) -> bool {
+        let to = from.into_direction(&direction);
+        if let Some(&gate_direction) = self.one_way_gates.get(&to) {
+            if direction != gate_direction {
+                return false;
+            }
+        }
+
+        if let Some(&gate_direction) = self.one_way_gates.get(&from) {
+            if direction == gate_direction.opposite() {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn teleporter_glyph() -> char {
+        '¤'
+    }
+
+    // Gates get their own arrows, so that they are not mistaken for room transitions.
+    pub fn gate_glyph(direction: Direction) -> char {
+        match direction {
+            Direction::North => '⇡',
+            Direction::East => '⇢',
+            Direction::South => '⇣',
+            Direction::West => '⇠',
+            Direction::NorthEast => '⇗',
+            Direction::SouthEast => '⇘',
+            Direction::SouthWest => '⇙',
+            Direction::NorthWest => '⇖',
+        }
+    }
+
+    pub fn entrance_glyph() -> char {
+        '←'
+    }
+
+    pub fn exit_glyph() -> char {
+        '→'
+    }
+
+    pub fn is_locked_exit(&self) -> bool {
+        self.exit.iter().any(|exit| self.doors.contains_key(exit))
+    }
+
+    pub fn is_walkable_position(&self, position: Position) -> bool {
+        self.is_valid_position(position) && !self.is_collapsed_position(position)
+    }
+
+    pub fn trap_at(&self, position: Position) -> Option<Trap> {
+        self.traps.get(&position).copied()
+    }
+
+    pub fn traps(&self) -> &HashMap<Position, Trap> {
+        &self.traps
+    }
+
+    pub fn is_collapsed_position(&self, position: Position) -> bool {
+        self.collapsed_positions
+            .get(&position)
+            .is_some_and(|instant| instant.elapsed() < Trap::COLLAPSE_DURATION)
+    }
+
+    pub fn collapsed_positions(&self) -> impl Iterator<Item = &Position> {
+        self.collapsed_positions
+            .iter()
+            .filter(|(_, instant)| instant.elapsed() < Trap::COLLAPSE_DURATION)
+            .map(|(position, _)| position)
+    }
+
+    // Collapses the corridor around the given position, sparing the position itself
+    // so that whoever triggered the collapse is not buried.
+    pub fn collapse_around(&mut self, position: Position) {
+        let (x, y) = position;
+        for cy in y.saturating_sub(1)..=y + 1 {
+            for cx in x.saturating_sub(1)..=x + 1 {
+                if (cx, cy) != position && self.is_valid_position((cx, cy)) {
+                    self.collapsed_positions.insert((cx, cy), Instant::now());
+                }
+            }
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.collapsed_positions
+            .retain(|_, instant| instant.elapsed() < Trap::COLLAPSE_DURATION);
+        self.light_sources.retain(|source| source.is_active());
+    }
+
     pub fn is_entrance_position(&self, position: Position) -> bool {
         self.entrance.contains(&position)
     }
@@ -854,8 +1467,12 @@ fn bresenham_line(from: (i32, i32), to: (i32, i32)) -> Vec<Position> {
 
 #[cfg(test)]
 mod tests {
-    use super::Maze;
+    use super::{Maze, MazeGenerationAlgorithm, MazeImageStyle, MazeTopology, Trap};
+    use crate::game::{Direction, Hero, Position};
     use crate::{game::MAX_MAZE_ID, AppResult};
+    use itertools::Itertools;
+    use std::collections::HashSet;
+    use strum::IntoEnumIterator;
 
     #[test]
     fn test_random_mazes_image() -> AppResult<()> {
@@ -866,4 +1483,151 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn test_traps_are_deterministic() {
+        let new_maze = || {
+            Maze::new(
+                5,
+                42,
+                16,
+                8,
+                None,
+                None,
+                MazeGenerationAlgorithm::DepthFirstSearch,
+                MazeTopology::Orthogonal,
+                MazeImageStyle::Straight,
+            )
+        };
+
+        let maze = new_maze();
+        assert!(!maze.traps().is_empty());
+        assert_eq!(maze.traps(), new_maze().traps());
+
+        for (&position, _) in maze.traps().iter() {
+            assert!(maze.is_valid_position(position));
+            assert!(!maze.is_entrance_position(position));
+            assert!(!maze.is_exit_position(position));
+        }
+
+        let reachable_positions = maze.reachable_positions(maze.entrance_positions()[0], |p| {
+            maze.trap_at(p) == Some(Trap::SpikePit)
+        });
+        assert!(maze
+            .exit_positions()
+            .iter()
+            .any(|exit| reachable_positions.contains(exit)));
+    }
+
+    #[test]
+    fn test_keys_are_reachable() {
+        for seed in 0..20 {
+            let maze = Maze::new(
+                8,
+                seed,
+                20,
+                12,
+                None,
+                None,
+                MazeGenerationAlgorithm::DepthFirstSearch,
+                MazeTopology::Orthogonal,
+                MazeImageStyle::Straight,
+            );
+
+            assert!(!maze.keys().is_empty());
+            for color in maze.keys().keys() {
+                assert!(maze.doors().values().any(|door_color| door_color == color));
+            }
+
+            // Doors stay locked until their key is held, and keys are picked up as
+            // soon as they can be reached.
+            let mut held_keys = HashSet::new();
+            let reachable_positions = loop {
+                let is_blocked = |position: Position| {
+                    maze.door_at(position)
+                        .is_some_and(|color| !held_keys.contains(&color))
+                        || maze.trap_at(position) == Some(Trap::SpikePit)
+                };
+                let reachable_positions =
+                    maze.reachable_positions(maze.entrance_positions()[0], is_blocked);
+
+                let new_keys = maze
+                    .keys()
+                    .iter()
+                    .filter(|(color, position)| {
+                        !held_keys.contains(*color) && reachable_positions.contains(*position)
+                    })
+                    .map(|(&color, _)| color)
+                    .collect_vec();
+                if new_keys.is_empty() {
+                    break reachable_positions;
+                }
+                held_keys.extend(new_keys);
+            };
+
+            for position in maze.keys().values() {
+                assert!(reachable_positions.contains(position));
+            }
+            assert!(maze
+                .exit_positions()
+                .iter()
+                .any(|exit| reachable_positions.contains(exit)));
+        }
+    }
+
+    #[test]
+    fn test_gates_and_teleporters_keep_exit_reachable() {
+        for seed in 0..20 {
+            let maze = Maze::new(
+                6,
+                seed,
+                20,
+                12,
+                None,
+                None,
+                MazeGenerationAlgorithm::DepthFirstSearch,
+                MazeTopology::Orthogonal,
+                MazeImageStyle::Straight,
+            );
+
+            for (&from, &to) in maze.teleporters().iter() {
+                assert_eq!(maze.teleporter_destination(to), Some(from));
+            }
+
+            for (&position, &direction) in maze.one_way_gates().iter() {
+                assert!(maze.is_move_allowed(position, direction));
+                assert!(!maze.is_move_allowed(position, direction.opposite()));
+            }
+
+            let path = maze.shortest_path(
+                maze.entrance_positions()[0],
+                maze.exit_positions(),
+                |position| maze.trap_at(position) == Some(Trap::SpikePit),
+            );
+            assert!(path.is_some());
+        }
+    }
+
+    #[test]
+    fn test_gate_glyphs_are_distinct() {
+        let other_glyphs = Trap::iter()
+            .map(|trap| trap.glyph())
+            .chain([
+                '·',
+                Trap::collapsed_glyph(),
+                Maze::teleporter_glyph(),
+                Maze::entrance_glyph(),
+                Maze::exit_glyph(),
+                Hero::MARK_GLYPH,
+                Hero::SHARED_MARK_GLYPH,
+            ])
+            .chain(Direction::iter().map(|direction| direction.arrow()))
+            .collect::<HashSet<char>>();
+
+        let gate_glyphs = Direction::iter()
+            .map(Maze::gate_glyph)
+            .collect::<HashSet<char>>();
+        assert_eq!(gate_glyphs.len(), Direction::iter().count());
+        assert!(gate_glyphs.is_disjoint(&other_glyphs));
+    }
 }
@@ -1,4 +1,6 @@
-use super::{entity::Entity, Direction, Hero, IntoDirection, Maze, Position};
+use super::{
+    entity::Entity, trap::Trap, Direction, Hero, IntoDirection, Maze, MinotaurSnapshot, Position,
+};
 use crate::PlayerId;
 use itertools::Itertools;
 use rand::{seq::IteratorRandom, Rng};
@@ -9,6 +11,7 @@ pub struct Minotaur {
     id: PlayerId,
     name: String,
     chasing: Option<PlayerId>,
+    alarm: Option<(Position, Instant)>,
     maze_id: usize,
     position: Position,
     direction: Direction,
@@ -41,6 +44,7 @@ impl Minotaur {
             id: PlayerId::new_v4(),
             name,
             chasing: None,
+            alarm: None,
             maze_id,
             position,
             direction: Direction::North,
@@ -52,6 +56,18 @@ impl Minotaur {
         }
     }
 
+    // Minotaurs of replays are only drawn, so their stats do not matter.
+    pub fn from_snapshot(snapshot: &MinotaurSnapshot, maze_id: usize, hero_id: PlayerId) -> Self {
+        Self {
+            id: snapshot.id,
+            name: snapshot.name.clone(),
+            chasing: snapshot.is_chasing.then_some(hero_id),
+            position: snapshot.position,
+            direction: snapshot.direction,
+            ..Self::new(snapshot.name.clone(), maze_id, snapshot.position, 0, 0, 0.0)
+        }
+    }
+
     pub fn update(&mut self, maze: &mut Maze, visible_heros: Vec<&Hero>) {
         if let Some(hero_id) = self.chasing {
             if !visible_heros.iter().map(|h| h.id()).any(|id| id == hero_id) {
@@ -61,6 +77,12 @@ impl Minotaur {
             self.chasing = Some(hero_id);
         }
 
+        if let Some((position, instant)) = self.alarm {
+            if position == self.position || instant.elapsed() > Trap::ALARM_DURATION {
+                self.alarm = None;
+            }
+        }
+
         if self.last_update_time.elapsed() < self.movement_recovery_duration() {
             return;
         }
@@ -81,25 +103,33 @@ impl Minotaur {
                 (y as isize + d.as_offset().1).max(0) as usize,
             );
             maze.is_valid_minotaur_position(new_position)
+                && maze.is_move_allowed(self.position, **d)
         })
         .collect_vec();
 
-        if let Some(hero_id) = self.chasing {
-            // Move toward chased hero
-            if let Some(hero) = visible_heros.iter().find(|h| h.id() == hero_id) {
-                let current_distance = self.position.distance(hero.position());
-                available_directions = available_directions
-                    .iter()
-                    .filter(|d| {
-                        let new_position = (
-                            (x as isize + d.as_offset().0).max(0) as usize,
-                            (y as isize + d.as_offset().1).max(0) as usize,
-                        );
-                        new_position.distance(hero.position()) < current_distance
-                    })
-                    .map(|d| *d)
-                    .collect_vec();
-            }
+        // Move toward chased hero, or toward the last triggered alarm.
+        let target = if let Some(hero_id) = self.chasing {
+            visible_heros
+                .iter()
+                .find(|h| h.id() == hero_id)
+                .map(|hero| hero.position())
+        } else {
+            self.alarm.map(|(position, _)| position)
+        };
+
+        if let Some(target) = target {
+            let current_distance = self.position.distance(target);
+            available_directions = available_directions
+                .iter()
+                .filter(|d| {
+                    let new_position = (
+                        (x as isize + d.as_offset().0).max(0) as usize,
+                        (y as isize + d.as_offset().1).max(0) as usize,
+                    );
+                    new_position.distance(target) < current_distance
+                })
+                .copied()
+                .collect_vec();
         }
 
         if available_directions.len() > 0 && rng.gen_bool(self.aggression) {
@@ -109,11 +139,17 @@ impl Minotaur {
                 (x as isize + direction.as_offset().0).max(0) as usize,
                 (y as isize + direction.as_offset().1).max(0) as usize,
             );
-            self.position = new_position;
+            self.position = maze
+                .teleporter_destination(new_position)
+                .unwrap_or(new_position);
             self.last_update_time = Instant::now();
         }
     }
 
+    pub fn alert(&mut self, position: Position) {
+        self.alarm = Some((position, Instant::now()));
+    }
+
     pub fn is_chasing(&self, player_id: PlayerId) -> bool {
         self.chasing == Some(player_id)
     }
@@ -1,21 +1,48 @@
+mod achievement;
 mod alarm_level;
+mod chat;
+mod config;
 mod direction;
+mod door;
 mod entity;
+mod events;
 mod game;
+mod ghost;
+mod hall_of_fame;
 mod hero;
+mod light;
 mod maze;
 mod minotaur;
 mod power_up;
+mod race;
+mod replay;
+mod stats;
+mod trap;
 mod utils;
 mod view;
 
+pub use achievement::Achievement;
 pub use alarm_level::AlarmLevel;
+pub use chat::{Chat, ChatChannel, ChatMessage, PingKind};
+pub use config::{GameConfig, HeroConfig, MinotaurConfig, RoomScaling};
 pub use direction::Direction;
+pub use door::KeyColor;
 pub use entity::Entity;
-pub use game::{Game, MAX_MAZE_ID};
+pub use events::{EventBus, GameEvent};
+pub use game::{Game, GameInstance, GameStores, HeroInteractions, MAX_MAZE_ID};
+pub use ghost::{Ghost, GhostStore};
+pub use hall_of_fame::{HallOfFame, HeroRecord, MinotaurRecord};
 pub use hero::{Hero, HeroCommand};
-pub use maze::Maze;
+pub use light::{LightSource, LightSourceKind};
+pub use maze::{Maze, MazeDescriptor};
 pub use power_up::PowerUp;
+pub use race::{Race, RaceResult};
+pub use replay::{
+    HeroSnapshot, MinotaurSnapshot, Replay, ReplayChunk, ReplayFrame, ReplayHeroState,
+};
+pub use stats::{HeroStats, StatsStore};
+pub use trap::Trap;
+pub(crate) use utils::to_player_name;
 pub use utils::GameColors;
 pub use view::View;
 
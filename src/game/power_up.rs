@@ -1,6 +1,7 @@
+use serde::{Deserialize, Serialize};
 use strum_macros::{Display, EnumIter};
 
-#[derive(Debug, Clone, Copy, Display, PartialEq, EnumIter)]
+#[derive(Debug, Clone, Copy, Display, PartialEq, Eq, Hash, EnumIter, Serialize, Deserialize)]
 pub enum PowerUp {
     Speed,
     Vision,
@@ -0,0 +1,54 @@
+use crate::PlayerId;
+use std::{
+    cmp::Ordering,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RaceResult {
+    pub hero_id: PlayerId,
+    pub name: String,
+    pub has_exited: bool,
+    // Deepest room reached, or the number of rooms for heros who exited the labyrinth.
+    pub maze_id: usize,
+    // Time to exit the labyrinth, or to reach the deepest room.
+    pub duration: Duration,
+}
+
+impl RaceResult {
+    // Heros who exited are ranked by time, the others by how deep and how fast they went.
+    fn rank(&self, other: &Self) -> Ordering {
+        other
+            .has_exited
+            .cmp(&self.has_exited)
+            .then(other.maze_id.cmp(&self.maze_id))
+            .then(self.duration.cmp(&other.duration))
+    }
+}
+
+// In race mode, heros gather and start together once the countdown ends.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Race {
+    Gathering,
+    Countdown { instant: Instant },
+    Running { instant: Instant },
+    Finished { results: Vec<RaceResult> },
+}
+
+impl Race {
+    pub const COUNTDOWN_DURATION: Duration = Duration::from_secs(5);
+    pub const TIME_LIMIT: Duration = Duration::from_secs(10 * 60);
+
+    pub fn can_start(&self) -> bool {
+        matches!(self, Self::Gathering | Self::Finished { .. })
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self, Self::Running { .. })
+    }
+
+    pub fn finish(mut results: Vec<RaceResult>) -> Self {
+        results.sort_by(|a, b| a.rank(b));
+        Self::Finished { results }
+    }
+}
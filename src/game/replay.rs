@@ -0,0 +1,362 @@
+use super::{
+    hero::HeroState, maze::MazeDescriptor, minotaur::Minotaur, Direction, Entity, GameConfig, Hero,
+    HeroCommand, Position,
+};
+use crate::{store_path, AppResult, PlayerId};
+use anyhow::anyhow;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ReplayHeroState {
+    WaitingToStart,
+    InMaze,
+    Dead { duration: Duration },
+    Victory { duration: Duration },
+}
+
+impl From<HeroState> for ReplayHeroState {
+    fn from(state: HeroState) -> Self {
+        match state {
+            HeroState::WaitingToStart => Self::WaitingToStart,
+            HeroState::InMaze { .. } => Self::InMaze,
+            HeroState::Dead { duration, .. } => Self::Dead { duration },
+            HeroState::Victory { duration, .. } => Self::Victory { duration },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeroSnapshot {
+    pub maze_id: usize,
+    pub position: Position,
+    pub direction: Direction,
+    pub vision: usize,
+    pub state: ReplayHeroState,
+}
+
+impl From<&Hero> for HeroSnapshot {
+    fn from(hero: &Hero) -> Self {
+        Self {
+            maze_id: hero.maze_id(),
+            position: hero.position(),
+            direction: hero.direction(),
+            vision: hero.vision(),
+            state: hero.state.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MinotaurSnapshot {
+    pub id: PlayerId,
+    pub name: String,
+    pub position: Position,
+    pub direction: Direction,
+    pub is_chasing: bool,
+}
+
+impl MinotaurSnapshot {
+    pub fn new(minotaur: &Minotaur, hero_id: PlayerId) -> Self {
+        Self {
+            id: minotaur.id(),
+            name: minotaur.name().to_string(),
+            position: minotaur.position(),
+            direction: minotaur.direction(),
+            is_chasing: minotaur.is_chasing(hero_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayHeader {
+    version: u32,
+    pub hero_id: PlayerId,
+    pub hero_name: String,
+    // Seconds since the unix epoch.
+    pub started_at: u64,
+    pub tick_duration: Duration,
+    // Config of the game, so that replays play by the same rules.
+    pub config: GameConfig,
+    pub mazes: Vec<MazeDescriptor>,
+}
+
+// Frames only hold what changed since the previous frame.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    pub tick: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<HeroCommand>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hero: Option<HeroSnapshot>,
+    // Minotaurs of the hero maze that changed or showed up.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub minotaurs: Vec<MinotaurSnapshot>,
+    // Minotaurs that are no longer in the hero maze.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed_minotaurs: Vec<PlayerId>,
+}
+
+impl ReplayFrame {
+    fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+            && self.hero.is_none()
+            && self.minotaurs.is_empty()
+            && self.removed_minotaurs.is_empty()
+    }
+
+    pub fn apply_minotaurs(&self, minotaurs: &mut Vec<MinotaurSnapshot>) {
+        minotaurs.retain(|minotaur| {
+            !self.removed_minotaurs.contains(&minotaur.id)
+                && !self
+                    .minotaurs
+                    .iter()
+                    .any(|changed| changed.id == minotaur.id)
+        });
+        minotaurs.extend(self.minotaurs.iter().cloned());
+    }
+}
+
+// Part of a replay to be appended to its file. Chunks of a replay must be written in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayChunk {
+    filename: String,
+    // Only the first chunk has the header, and creates the file.
+    header: Option<ReplayHeader>,
+    frames: Vec<ReplayFrame>,
+}
+
+impl ReplayChunk {
+    // Replays are written as JSON lines: the header first, then one frame per line.
+    pub fn write(&self) -> AppResult<PathBuf> {
+        let directory = store_path(Replay::DIRECTORY)?;
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join(&self.filename);
+
+        let file = if let Some(header) = self.header.as_ref() {
+            Replay::prune(&directory)?;
+            let mut file = std::fs::File::create(&path)?;
+            serde_json::to_writer(&mut file, header)?;
+            file.write_all(b"\n")?;
+            file
+        } else {
+            std::fs::OpenOptions::new().append(true).open(&path)?
+        };
+
+        let mut writer = BufWriter::new(file);
+        for frame in self.frames.iter() {
+            serde_json::to_writer(&mut writer, frame)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+
+        Ok(path)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub header: ReplayHeader,
+    // While recording, only the frames that have not been handed out to be written yet.
+    pub frames: Vec<ReplayFrame>,
+    first_tick: u64,
+    last_hero: Option<HeroSnapshot>,
+    last_minotaurs: HashMap<PlayerId, MinotaurSnapshot>,
+    pending_commands: Vec<HeroCommand>,
+    // Whether the first chunk, with the header, has been handed out.
+    is_started: bool,
+}
+
+impl Replay {
+    const VERSION: u32 = 3;
+    const DIRECTORY: &'static str = "replays";
+    // Recorded frames are handed out to be written in chunks of this many frames.
+    const CHUNK_FRAMES: usize = 256;
+    // Only the most recent replays are kept on disk.
+    pub const MAX_REPLAYS: usize = 1000;
+
+    pub fn new(
+        hero: &Hero,
+        started_at: u64,
+        first_tick: u64,
+        tick_duration: Duration,
+        config: GameConfig,
+        mazes: Vec<MazeDescriptor>,
+    ) -> Self {
+        Self {
+            header: ReplayHeader {
+                version: Self::VERSION,
+                hero_id: hero.id(),
+                hero_name: hero.name().to_string(),
+                started_at,
+                tick_duration,
+                config,
+                mazes,
+            },
+            frames: vec![],
+            first_tick,
+            last_hero: None,
+            last_minotaurs: HashMap::new(),
+            pending_commands: vec![],
+            is_started: false,
+        }
+    }
+
+    pub fn record_command(&mut self, command: HeroCommand) {
+        self.pending_commands.push(command);
+    }
+
+    // Ticks are stored relative to the start of the run.
+    pub fn record_frame(
+        &mut self,
+        tick: u64,
+        hero: HeroSnapshot,
+        minotaurs: Vec<MinotaurSnapshot>,
+    ) {
+        let mut frame = ReplayFrame {
+            tick: tick.saturating_sub(self.first_tick),
+            commands: std::mem::take(&mut self.pending_commands),
+            ..Default::default()
+        };
+
+        if self.last_hero.as_ref() != Some(&hero) {
+            frame.hero = Some(hero.clone());
+            self.last_hero = Some(hero);
+        }
+
+        frame.removed_minotaurs = self
+            .last_minotaurs
+            .keys()
+            .filter(|id| !minotaurs.iter().any(|minotaur| minotaur.id == **id))
+            .copied()
+            .collect_vec();
+        for id in frame.removed_minotaurs.iter() {
+            self.last_minotaurs.remove(id);
+        }
+
+        for minotaur in minotaurs {
+            if self.last_minotaurs.get(&minotaur.id) != Some(&minotaur) {
+                frame.minotaurs.push(minotaur.clone());
+                self.last_minotaurs.insert(minotaur.id, minotaur);
+            }
+        }
+
+        if !frame.is_empty() {
+            self.frames.push(frame);
+        }
+    }
+
+    // Runs in which the hero never moved are not worth saving.
+    pub fn is_worth_saving(&self) -> bool {
+        self.is_started
+            || self.frames.iter().any(|frame| {
+                frame
+                    .hero
+                    .as_ref()
+                    .is_some_and(|hero| hero.state != ReplayHeroState::WaitingToStart)
+            })
+    }
+
+    // Hands out the recorded frames once there are enough of them, or all of them
+    // when the run is over, so that they do not pile up in memory.
+    pub fn take_chunk(&mut self, is_over: bool) -> Option<ReplayChunk> {
+        if self.frames.is_empty() || (!is_over && self.frames.len() < Self::CHUNK_FRAMES) {
+            return None;
+        }
+
+        if !self.is_worth_saving() {
+            // Start over from a full frame, dropping the wait before the run.
+            self.frames.clear();
+            self.last_hero = None;
+            self.last_minotaurs.clear();
+            return None;
+        }
+
+        let header = (!self.is_started).then(|| self.header.clone());
+        self.is_started = true;
+        Some(ReplayChunk {
+            filename: self.filename(),
+            header,
+            frames: std::mem::take(&mut self.frames),
+        })
+    }
+
+    pub fn last_tick(&self) -> u64 {
+        self.frames
+            .last()
+            .map(|frame| frame.tick)
+            .unwrap_or_default()
+    }
+
+    fn filename(&self) -> String {
+        let name = self
+            .header
+            .hero_name
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+
+        format!(
+            "{}_{}_{}.jsonl",
+            self.header.started_at,
+            name,
+            &self.header.hero_id.simple().to_string()[..8]
+        )
+    }
+
+    // Makes room for a new replay by removing the oldest ones. Filenames start
+    // with the start timestamp, so they sort from the oldest.
+    fn prune(directory: &Path) -> AppResult<()> {
+        let paths = std::fs::read_dir(directory)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "jsonl")
+            })
+            .sorted()
+            .collect_vec();
+
+        for path in paths
+            .iter()
+            .take((paths.len() + 1).saturating_sub(Self::MAX_REPLAYS))
+        {
+            std::fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> AppResult<Self> {
+        let mut lines = BufReader::new(std::fs::File::open(path)?).lines();
+
+        let header: ReplayHeader = serde_json::from_str(
+            &lines
+                .next()
+                .ok_or_else(|| anyhow!("Empty replay file {}", path.display()))??,
+        )?;
+        if header.version != Self::VERSION {
+            return Err(anyhow!("Unsupported replay version {}", header.version));
+        }
+
+        let mut frames = vec![];
+        for line in lines {
+            frames.push(serde_json::from_str(&line?)?);
+        }
+
+        Ok(Self {
+            header,
+            frames,
+            first_tick: 0,
+            last_hero: None,
+            last_minotaurs: HashMap::new(),
+            pending_commands: vec![],
+            is_started: true,
+        })
+    }
+}
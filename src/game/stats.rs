@@ -0,0 +1,106 @@
+use super::{Achievement, PowerUp};
+use crate::{backup_json, load_json, save_json, AppResult, PlayerId};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HeroStats {
+    pub runs_started: usize,
+    pub deaths_per_room: HashMap<usize, usize>,
+    pub killers: HashMap<String, usize>,
+    pub power_ups_collected: HashMap<PowerUp, usize>,
+    pub distance_walked: usize,
+    pub fastest_clears: HashMap<usize, Duration>,
+    pub play_time: Duration,
+    #[serde(default)]
+    pub achievements: HashSet<Achievement>,
+}
+
+impl HeroStats {
+    pub fn record_death(&mut self, maze_id: usize, killer: Option<&str>) {
+        *self.deaths_per_room.entry(maze_id).or_default() += 1;
+        if let Some(name) = killer {
+            *self.killers.entry(name.to_string()).or_default() += 1;
+        }
+    }
+
+    pub fn record_power_up(&mut self, power_up: PowerUp) {
+        *self.power_ups_collected.entry(power_up).or_default() += 1;
+    }
+
+    pub fn record_clear(&mut self, maze_id: usize, duration: Duration) {
+        let fastest = self.fastest_clears.entry(maze_id).or_insert(duration);
+        if duration < *fastest {
+            *fastest = duration;
+        }
+    }
+
+    // Returns true if the achievement was not unlocked before.
+    pub fn unlock(&mut self, achievement: Achievement) -> bool {
+        self.achievements.insert(achievement)
+    }
+
+    pub fn total_deaths(&self) -> usize {
+        self.deaths_per_room.values().sum()
+    }
+
+    pub fn top_killer(&self) -> Option<(&str, usize)> {
+        self.killers
+            .iter()
+            .max_by_key(|(_, &kills)| kills)
+            .map(|(name, &kills)| (name.as_str(), kills))
+    }
+}
+
+// Lifetime stats of registered players, keyed by account id.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatsStore {
+    version: u32,
+    pub stats: HashMap<PlayerId, HeroStats>,
+}
+
+impl Default for StatsStore {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            stats: HashMap::new(),
+        }
+    }
+}
+
+impl StatsStore {
+    const VERSION: u32 = 1;
+    const FILENAME: &'static str = "stats.json";
+
+    pub fn new(stats: HashMap<PlayerId, HeroStats>) -> Self {
+        Self {
+            version: Self::VERSION,
+            stats,
+        }
+    }
+
+    pub fn load() -> AppResult<Self> {
+        let Some(store) = load_json::<Self>(Self::FILENAME)? else {
+            return Ok(Self::default());
+        };
+
+        if store.version != Self::VERSION {
+            return Err(anyhow!("Unsupported stats version {}", store.version));
+        }
+
+        Ok(store)
+    }
+
+    pub fn save(&self) -> AppResult<()> {
+        save_json(Self::FILENAME, self)
+    }
+
+    pub fn backup() -> AppResult<PathBuf> {
+        backup_json(Self::FILENAME)
+    }
+}
@@ -0,0 +1,32 @@
+use std::time::Duration;
+use strum_macros::{Display, EnumIter};
+
+#[derive(Debug, Clone, Copy, Display, PartialEq, Eq, Hash, EnumIter)]
+pub enum Trap {
+    SpikePit,
+    SlowTile,
+    AlarmPlate,
+    CollapsingFloor,
+}
+
+impl Trap {
+    // How long a hero is slowed down after stepping on a slow tile.
+    pub const SLOW_DURATION: Duration = Duration::from_secs(3);
+    // How long collapsed positions stay impassable.
+    pub const COLLAPSE_DURATION: Duration = Duration::from_secs(4);
+    // How long minotaurs keep searching the position of a triggered alarm plate.
+    pub const ALARM_DURATION: Duration = Duration::from_secs(10);
+
+    pub fn glyph(&self) -> char {
+        match self {
+            Self::SpikePit => '^',
+            Self::SlowTile => '~',
+            Self::AlarmPlate => '!',
+            Self::CollapsingFloor => '%',
+        }
+    }
+
+    pub fn collapsed_glyph() -> char {
+        '#'
+    }
+}
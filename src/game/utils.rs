@@ -24,6 +24,10 @@ impl GameColors {
     pub const MINOTAUR: Rgba<u8> = Rgba([225, 203, 3, 255]);
     pub const CHASING_MINOTAUR: Rgba<u8> = Rgba([255, 15, 0, 255]);
     pub const POWER_UP: Rgba<u8> = Rgba([255, 180, 244, 255]);
+    pub const TORCH: Rgba<u8> = Rgba([255, 140, 0, 255]);
+    pub const FLARE: Rgba<u8> = Rgba([255, 250, 205, 255]);
+    pub const GHOST: Rgba<u8> = Rgba([35, 35, 255, 110]);
+    pub const DEAD_TEAMMATE: Rgba<u8> = Rgba([120, 120, 120, 255]);
 }
 
 pub const MINOTAUR_NAMES: [&'static str; 7] = [
@@ -1,8 +1,12 @@
+mod accounts;
 mod constants;
 pub mod game;
+pub mod replay_viewer;
 pub mod ssh;
 mod tui;
 pub mod ui;
 mod utils;
 
-pub use utils::{store_path, AppResult, PlayerId};
+pub use utils::{
+    backup_json, load_json, load_store, now_timestamp, save_json, store_path, AppResult, PlayerId,
+};
@@ -1,5 +1,11 @@
-use asterion::{ssh::AppServer, store_path, AppResult};
-use clap::{ArgAction, Parser};
+use anyhow::anyhow;
+use asterion::{
+    game::GameConfig,
+    replay_viewer,
+    ssh::{AppServer, ServerOptions},
+    store_path, AppResult,
+};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
 use log::LevelFilter;
 use log4rs::{
     append::file::FileAppender,
@@ -7,19 +13,80 @@ use log4rs::{
     encode::pattern::PatternEncoder,
     Config,
 };
+use russh::keys::{Algorithm, EcdsaCurve, HashAlg};
+use std::{net::IpAddr, path::PathBuf};
 
 const DEFAULT_PORT: u16 = 2020;
 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum KeyAlgorithm {
+    Ed25519,
+    Ecdsa,
+    Rsa,
+}
+
+impl KeyAlgorithm {
+    fn algorithm(&self) -> Algorithm {
+        match self {
+            Self::Ed25519 => Algorithm::Ed25519,
+            Self::Ecdsa => Algorithm::Ecdsa {
+                curve: EcdsaCurve::NistP256,
+            },
+            Self::Rsa => Algorithm::Rsa {
+                hash: Some(HashAlg::Sha512),
+            },
+        }
+    }
+}
+
 #[derive(Parser, Debug)]
 #[clap(name="Asterion", about = "Find your way in da maze", author, version, long_about = None)]
 struct Args {
     #[clap(long, short = 'p', action=ArgAction::Set, help = "Set port to listen on")]
     port: Option<u16>,
+    #[clap(long, short = 'a', action=ArgAction::Set, default_value = "0.0.0.0", help = "Set address to listen on, IPv4 or IPv6")]
+    address: IpAddr,
+    #[clap(long, action=ArgAction::Set, default_value = "./keys", help = "Set host key file, created if missing")]
+    host_key: PathBuf,
+    #[clap(long, value_enum, default_value_t = KeyAlgorithm::Ed25519, help = "Set algorithm of a newly created host key")]
+    key_algorithm: KeyAlgorithm,
+    #[clap(long, action=ArgAction::Set, help = "Set log file [default: minotaur.log in the config directory]")]
+    log_file: Option<PathBuf>,
+    #[clap(long, action=ArgAction::Set, default_value = "info", help = "Set log level")]
+    log_level: LevelFilter,
+    #[clap(long, action=ArgAction::Set, help = "Set maximum number of heros playing at once")]
+    max_players: Option<usize>,
+    #[clap(long, action=ArgAction::Set, default_value_t = 4, help = "Set maximum number of open connections from the same IP")]
+    max_connections_per_ip: usize,
+    #[clap(long, action=ArgAction::Set, default_value_t = 20, help = "Set maximum number of connection attempts per minute from the same IP")]
+    max_connections_per_minute: usize,
+    #[clap(long, action=ArgAction::Set, help = "Set seed of the public labyrinth rooms")]
+    seed: Option<u64>,
+    #[clap(long, short = 'c', action=ArgAction::Set, help = "Load game rules from a TOML file")]
+    config: Option<PathBuf>,
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[clap(about = "Play back a recorded run")]
+    Replay { file: PathBuf },
 }
 
 #[tokio::main]
 async fn main() -> AppResult<()> {
-    let logfile_path = store_path("minotaur.log")?;
+    let args = Args::parse();
+
+    // Replays are played locally, without touching the server log.
+    if let Some(Command::Replay { file }) = args.command {
+        return replay_viewer::run(&file);
+    }
+
+    let logfile_path = match args.log_file {
+        Some(path) => path,
+        None => store_path("minotaur.log")?,
+    };
     let logfile = FileAppender::builder()
         .append(false)
         .encoder(Box::new(PatternEncoder::new("{l} - {m}\n")))
@@ -27,12 +94,27 @@ async fn main() -> AppResult<()> {
 
     let config = Config::builder()
         .appender(Appender::builder().build("logfile", Box::new(logfile)))
-        .build(Root::builder().appender("logfile").build(LevelFilter::Info))?;
+        .build(Root::builder().appender("logfile").build(args.log_level))?;
 
     log4rs::init_config(config)?;
 
-    let port = Args::parse().port.unwrap_or(DEFAULT_PORT);
-    let mut game_server = AppServer::new(port);
+    let game_config = match args.config {
+        Some(path) => GameConfig::load(&path)
+            .map_err(|e| anyhow!("Could not load config {}: {}", path.display(), e))?,
+        None => GameConfig::default(),
+    };
+
+    let options = ServerOptions {
+        address: args.address,
+        port: args.port.unwrap_or(DEFAULT_PORT),
+        host_key_path: args.host_key,
+        key_algorithm: args.key_algorithm.algorithm(),
+        max_players: args.max_players,
+        max_connections_per_ip: args.max_connections_per_ip,
+        max_connections_per_minute: args.max_connections_per_minute,
+        seed: args.seed,
+    };
+    let mut game_server = AppServer::new(options, game_config);
     game_server.run().await?;
 
     Ok(())
@@ -0,0 +1,260 @@
+use crate::{
+    game::{Game, HeroSnapshot, MinotaurSnapshot, Replay, ReplayHeroState},
+    ui::ui::{self, UiScreen},
+    AppResult,
+};
+use crossterm::{
+    cursor::{Hide, Show},
+    event::{self, Event, KeyCode, KeyEventKind},
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{prelude::CrosstermBackend, Terminal};
+use std::{
+    io::Stdout,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+const SEEK_DURATION: Duration = Duration::from_secs(5);
+const MIN_SPEED: f64 = 0.25;
+const MAX_SPEED: f64 = 16.0;
+
+// Plays a recorded run back locally, rebuilding the game state at the current tick.
+struct ReplayViewer {
+    replay: Replay,
+    game: Game,
+    // Current position, in ticks from the start of the run.
+    tick: f64,
+    // Index of the next frame to apply to the game.
+    next_frame: usize,
+    run_started_tick: u64,
+    hero: Option<HeroSnapshot>,
+    minotaurs: Vec<MinotaurSnapshot>,
+    is_playing: bool,
+    speed: f64,
+    screen: UiScreen,
+}
+
+impl ReplayViewer {
+    fn new(replay: Replay) -> AppResult<Self> {
+        let game = Game::from_replay(&replay)?;
+        let run_started_tick = replay
+            .frames
+            .iter()
+            .find(|frame| {
+                frame
+                    .hero
+                    .as_ref()
+                    .is_some_and(|hero| hero.state == ReplayHeroState::InMaze)
+            })
+            .map(|frame| frame.tick)
+            .unwrap_or_default();
+
+        let mut viewer = Self {
+            replay,
+            game,
+            tick: 0.0,
+            next_frame: 0,
+            run_started_tick,
+            hero: None,
+            minotaurs: vec![],
+            is_playing: true,
+            speed: 1.0,
+            screen: UiScreen::Game,
+        };
+        viewer.seek(0.0)?;
+
+        Ok(viewer)
+    }
+
+    fn ticks_per_second(&self) -> f64 {
+        1.0 / self.replay.header.tick_duration.as_secs_f64()
+    }
+
+    fn last_tick(&self) -> f64 {
+        self.replay.last_tick() as f64
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.replay
+            .header
+            .tick_duration
+            .mul_f64((self.tick - self.run_started_tick as f64).max(0.0))
+    }
+
+    // Applies the frames up to the given tick. Seeking backwards rebuilds the game,
+    // so that the hero memory of the labyrinth matches the run.
+    fn seek(&mut self, tick: f64) -> AppResult<()> {
+        let tick = tick.clamp(0.0, self.last_tick());
+        if tick < self.tick {
+            self.game = Game::from_replay(&self.replay)?;
+            self.next_frame = 0;
+            self.hero = None;
+            self.minotaurs.clear();
+        }
+        self.tick = tick;
+
+        let hero_id = self.replay.header.hero_id;
+        while let Some(frame) = self.replay.frames.get(self.next_frame) {
+            if frame.tick as f64 > tick {
+                break;
+            }
+
+            frame.apply_minotaurs(&mut self.minotaurs);
+            if let Some(hero) = frame.hero.as_ref() {
+                self.hero = Some(hero.clone());
+            }
+            if let Some(hero) = self.hero.as_ref() {
+                let elapsed = self
+                    .replay
+                    .header
+                    .tick_duration
+                    .mul_f64(frame.tick.saturating_sub(self.run_started_tick) as f64);
+                self.game
+                    .set_replay_state(hero_id, hero, elapsed, &self.minotaurs);
+            }
+            self.next_frame += 1;
+        }
+
+        // Keep the timer running between frames.
+        if let Some(hero) = self.hero.as_ref() {
+            self.game
+                .set_replay_state(hero_id, hero, self.elapsed(), &self.minotaurs);
+        }
+
+        Ok(())
+    }
+
+    fn step(&mut self, forward: bool) -> AppResult<()> {
+        self.is_playing = false;
+        let current = self.tick.floor() as u64;
+        let target = if forward {
+            self.replay
+                .frames
+                .iter()
+                .map(|frame| frame.tick)
+                .find(|&tick| tick > current)
+        } else {
+            self.replay
+                .frames
+                .iter()
+                .map(|frame| frame.tick)
+                .rev()
+                .find(|&tick| tick < current)
+        };
+
+        if let Some(tick) = target {
+            self.seek(tick as f64)?;
+        }
+
+        Ok(())
+    }
+
+    fn advance(&mut self, elapsed: Duration) -> AppResult<()> {
+        if !self.is_playing {
+            return Ok(());
+        }
+
+        let tick = self.tick + elapsed.as_secs_f64() * self.ticks_per_second() * self.speed;
+        if tick >= self.last_tick() {
+            self.is_playing = false;
+        }
+        self.seek(tick)
+    }
+
+    // Returns false when the viewer should quit.
+    fn handle_key(&mut self, key_code: KeyCode) -> AppResult<bool> {
+        let seek_ticks = SEEK_DURATION.as_secs_f64() * self.ticks_per_second();
+        match key_code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Char(' ') => {
+                if !self.is_playing && self.tick >= self.last_tick() {
+                    self.seek(0.0)?;
+                }
+                self.is_playing = !self.is_playing;
+            }
+            KeyCode::Char('+') => self.speed = (self.speed * 2.0).min(MAX_SPEED),
+            KeyCode::Char('-') => self.speed = (self.speed / 2.0).max(MIN_SPEED),
+            KeyCode::Char('.') => self.step(true)?,
+            KeyCode::Char(',') => self.step(false)?,
+            KeyCode::Right => self.seek(self.tick + seek_ticks)?,
+            KeyCode::Left => self.seek(self.tick - seek_ticks)?,
+            KeyCode::Char('0') => self.seek(0.0)?,
+            KeyCode::Char('g') => {
+                self.screen = match self.screen {
+                    UiScreen::GodView => UiScreen::Game,
+                    _ => UiScreen::GodView,
+                }
+            }
+            _ => {}
+        }
+
+        Ok(true)
+    }
+
+    fn status(&self) -> String {
+        let tick_duration = self.replay.header.tick_duration;
+        format!(
+            " {} {} x{}  {:.1}s/{:.1}s  space: play/pause  +/-: speed  ,/.: step  ←/→: seek  0: restart  g: god view  q: quit ",
+            self.replay.header.hero_name,
+            if self.is_playing { "▶" } else { "⏸" },
+            self.speed,
+            tick_duration.mul_f64(self.tick).as_secs_f64(),
+            tick_duration.mul_f64(self.last_tick()).as_secs_f64(),
+        )
+    }
+
+    fn draw(&self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> AppResult<()> {
+        // Skip the title screen.
+        let start_instant = Instant::now() - Duration::from_secs(2);
+        let hero_id = self.replay.header.hero_id;
+        let status = self.status();
+
+        terminal.draw(|frame| {
+            ui::render(frame, &self.game, hero_id, self.screen, start_instant)
+                .expect("Error while rendering replay.");
+
+            ui::render_status_line(frame, status);
+        })?;
+
+        Ok(())
+    }
+
+    fn run(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> AppResult<()> {
+        let tick_duration = self.replay.header.tick_duration;
+        let mut last_instant = Instant::now();
+
+        loop {
+            self.draw(terminal)?;
+
+            if event::poll(tick_duration)? {
+                if let Event::Key(key_event) = event::read()? {
+                    if key_event.kind == KeyEventKind::Press && !self.handle_key(key_event.code)? {
+                        return Ok(());
+                    }
+                }
+            }
+
+            let now = Instant::now();
+            self.advance(now - last_instant)?;
+            last_instant = now;
+        }
+    }
+}
+
+pub fn run(path: &Path) -> AppResult<()> {
+    let replay = Replay::load(path)?;
+    let mut viewer = ReplayViewer::new(replay)?;
+
+    let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stdout()))?;
+    terminal::enable_raw_mode()?;
+    crossterm::execute!(terminal.backend_mut(), EnterAlternateScreen, Hide)?;
+
+    let result = viewer.run(&mut terminal);
+
+    // Restore the terminal even if playing the replay failed.
+    crossterm::execute!(terminal.backend_mut(), LeaveAlternateScreen, Show)?;
+    terminal::disable_raw_mode()?;
+
+    result
+}
@@ -1,4 +1,3 @@
-use super::SSHEventHandler;
 use crate::AppResult;
 use anyhow::{anyhow, Result};
 use russh::server::Handle;
@@ -109,20 +108,4 @@ impl AppChannel {
 
         Ok(stdin_rx)
     }
-
-    pub async fn window_change_request(&mut self, width: u32, height: u32) -> Result<()> {
-        let AppChannelState::Ready { stdin } = &mut self.state else {
-            return Err(anyhow!("pty hasn't been allocated yet"));
-        };
-
-        let width = width.min(255);
-        let height = height.min(255);
-
-        stdin
-            .send(vec![SSHEventHandler::CMD_RESIZE, width as u8, height as u8])
-            .await
-            .map_err(|_| anyhow!("lost ui"))?;
-
-        Ok(())
-    }
 }
@@ -1,47 +1,129 @@
 use super::channel::AppChannel;
+use super::limiter::ConnectionGuard;
 use super::SSHEventHandler;
 use super::SSHWriterProxy;
 use super::TerminalEvent;
+use crate::accounts::AccountStore;
 use crate::tui::Tui;
 use crate::AppResult;
 use anyhow::anyhow;
 use anyhow::Context;
+use russh::keys::HashAlg;
 use russh::{
     server::{self, *},
     ChannelId,
 };
-use russh::{Channel, Pty};
+use russh::{Channel, MethodKind, MethodSet, Pty};
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc::Sender;
+use tokio::task;
 use tokio_util::sync::CancellationToken;
 
 pub type PlayerId = uuid::Uuid;
 
+// Logging in with this username watches the game without playing.
+const SPECTATOR_USERNAME: &str = "watch";
+
 pub struct AppClient {
     player_id: PlayerId,
     username: String,
+    is_registered: bool,
+    is_spectator: bool,
+    rejected_none: bool,
+    account_store: Arc<Mutex<AccountStore>>,
+    // Given back to the account store when the session ends.
+    guest_name: Option<String>,
     client_sender: Sender<Tui>,
     terminal_event_sender: Sender<(PlayerId, TerminalEvent)>,
     server_shutdown: CancellationToken,
     channels: HashMap<ChannelId, AppChannel>,
+    // Held until the session ends, so that it counts as open.
+    _connection: Option<ConnectionGuard>,
+    // Why the connection is refused, shown to the client before authentication fails.
+    rejection: Option<String>,
 }
 
 impl AppClient {
     pub fn new(
         server_shutdown: CancellationToken,
+        account_store: Arc<Mutex<AccountStore>>,
         client_sender: Sender<Tui>,
         terminal_event_sender: Sender<(PlayerId, TerminalEvent)>,
     ) -> Self {
         AppClient {
             player_id: PlayerId::new_v4(),
             username: "".into(),
+            is_registered: false,
+            is_spectator: false,
+            rejected_none: false,
+            account_store,
+            guest_name: None,
             client_sender,
             terminal_event_sender,
             server_shutdown,
             channels: HashMap::new(),
+            _connection: None,
+            rejection: None,
         }
     }
 
+    pub fn track_connection(&mut self, connection: ConnectionGuard) {
+        self._connection = Some(connection);
+    }
+
+    pub fn reject(&mut self, reason: String) {
+        self.rejection = Some(reason);
+    }
+
+    // Players and spectators without a key get a name that no account or other
+    // guest uses, until they disconnect.
+    fn login_guest(&mut self, user: &str) -> AppResult<()> {
+        let name = self
+            .account_store
+            .lock()
+            .map_err(|e| anyhow!("Account store lock poisoned: {}", e))?
+            .add_guest(user);
+        self.username = name.clone();
+        self.guest_name = Some(name);
+        self.is_spectator = user == SPECTATOR_USERNAME;
+        Ok(())
+    }
+
+    // Players with a key log into their account, which is created on their first
+    // login. Spectators only go by the name of an existing account, so that
+    // watching never creates one, and keep a player id of their own.
+    fn login_account(&mut self, user: &str, fingerprint: &str) -> AppResult<()> {
+        let mut account_store = self
+            .account_store
+            .lock()
+            .map_err(|e| anyhow!("Account store lock poisoned: {}", e))?;
+
+        if user == SPECTATOR_USERNAME {
+            let Some(account) = account_store.account(fingerprint) else {
+                drop(account_store);
+                return self.login_guest(user);
+            };
+            self.username = account.name.clone();
+            self.is_spectator = true;
+            return Ok(());
+        }
+
+        let account = account_store.login(fingerprint, user);
+        drop(account_store);
+        let account_store = self.account_store.clone();
+        task::spawn_blocking(move || {
+            if let Err(e) = AccountStore::save_shared(&account_store) {
+                log::error!("Error saving accounts: {}", e);
+            }
+        });
+
+        self.player_id = account.id;
+        self.username = account.name;
+        self.is_registered = true;
+        Ok(())
+    }
+
     fn channel_mut(&mut self, id: ChannelId) -> AppResult<&mut AppChannel> {
         self.channels
             .get_mut(&id)
@@ -67,14 +149,18 @@ impl AppClient {
             client_shutdown.clone(),
             self.server_shutdown.clone(),
         );
-        let tui = Tui::new(
+        let mut tui = Tui::new(
             self.player_id,
             self.username.clone(),
+            self.is_registered,
             width,
             height,
             writer,
             client_shutdown,
         )?;
+        if self.is_spectator {
+            tui.set_spectator();
+        }
 
         self.client_sender.send(tui).await?;
 
@@ -82,25 +168,95 @@ impl AppClient {
     }
 }
 
+impl Drop for AppClient {
+    fn drop(&mut self) {
+        if let Some(name) = self.guest_name.take() {
+            if let Ok(mut account_store) = self.account_store.lock() {
+                account_store.remove_guest(&name);
+            }
+        }
+    }
+}
+
 impl server::Handler for AppClient {
     type Error = anyhow::Error;
 
+    async fn authentication_banner(&mut self) -> Result<Option<String>, Self::Error> {
+        Ok(self
+            .rejection
+            .as_ref()
+            .map(|reason| format!("{}\r\n", reason)))
+    }
+
     async fn auth_none(&mut self, user: &str) -> Result<Auth, Self::Error> {
-        self.username = user.to_string();
+        if self.rejection.is_some() {
+            return Ok(Auth::Reject {
+                proceed_with_methods: Some(MethodSet::empty()),
+            });
+        }
+
+        // Clients try `none` first: reject it once so that clients with a key
+        // can log into their account. Clients without a key then go through
+        // keyboard-interactive, which is accepted without asking anything, and
+        // clients retrying `none` are accepted too. Both play as guests.
+        if !self.rejected_none {
+            self.rejected_none = true;
+            return Ok(Auth::Reject {
+                proceed_with_methods: Some(MethodSet::from(
+                    &[
+                        MethodKind::PublicKey,
+                        MethodKind::KeyboardInteractive,
+                        MethodKind::Password,
+                        MethodKind::None,
+                    ][..],
+                )),
+            });
+        }
+
+        self.login_guest(user)?;
+        Ok(Auth::Accept)
+    }
+
+    async fn auth_keyboard_interactive<'a>(
+        &'a mut self,
+        user: &str,
+        _submethods: &str,
+        _response: Option<Response<'a>>,
+    ) -> Result<Auth, Self::Error> {
+        if self.rejection.is_some() {
+            return Ok(Auth::Reject {
+                proceed_with_methods: Some(MethodSet::empty()),
+            });
+        }
+
+        self.login_guest(user)?;
         Ok(Auth::Accept)
     }
 
     async fn auth_password(&mut self, user: &str, _password: &str) -> Result<Auth, Self::Error> {
-        self.username = user.to_string();
+        if self.rejection.is_some() {
+            return Ok(Auth::Reject {
+                proceed_with_methods: Some(MethodSet::empty()),
+            });
+        }
+
+        self.login_guest(user)?;
         Ok(Auth::Accept)
     }
 
     async fn auth_publickey(
         &mut self,
         user: &str,
-        _public_key: &russh::keys::PublicKey,
+        public_key: &russh::keys::PublicKey,
     ) -> Result<Auth, Self::Error> {
-        self.username = user.to_string();
+        if self.rejection.is_some() {
+            return Ok(Auth::Reject {
+                proceed_with_methods: Some(MethodSet::empty()),
+            });
+        }
+
+        let fingerprint = public_key.fingerprint(HashAlg::Sha256).to_string();
+        self.login_account(user, &fingerprint)?;
         Ok(Auth::Accept)
     }
 
@@ -113,7 +269,7 @@ impl server::Handler for AppClient {
         let created = self.channels.insert(channel.id(), app_channel).is_none();
 
         if created {
-            println!("{} joined the game", self.username);
+            log::info!("{} joined the game", self.username);
             Ok(true)
         } else {
             Err(anyhow!(
@@ -163,8 +319,15 @@ impl server::Handler for AppClient {
         _: u32,
         _: &mut Session,
     ) -> AppResult<()> {
-        self.channel_mut(id)?
-            .window_change_request(width, height)
+        self.channel_mut(id)?;
+
+        // Sent apart from the terminal input, which can hold any byte.
+        let event = TerminalEvent::Resize {
+            width: width.min(u16::MAX as u32) as u16,
+            height: height.min(u16::MAX as u32) as u16,
+        };
+        self.terminal_event_sender
+            .send((self.player_id, event))
             .await?;
 
         Ok(())
@@ -0,0 +1,86 @@
+use crate::AppResult;
+use anyhow::anyhow;
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+// Open connections and recent connection attempts of each IP.
+#[derive(Debug)]
+pub struct ConnectionLimiter {
+    max_connections_per_ip: usize,
+    max_connections_per_minute: usize,
+    open: HashMap<IpAddr, usize>,
+    attempts: HashMap<IpAddr, Vec<Instant>>,
+}
+
+impl ConnectionLimiter {
+    const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+    pub fn new(max_connections_per_ip: usize, max_connections_per_minute: usize) -> Self {
+        Self {
+            max_connections_per_ip,
+            max_connections_per_minute,
+            open: HashMap::new(),
+            attempts: HashMap::new(),
+        }
+    }
+
+    // Rejected attempts count too, so that clients hammering the server stay out.
+    fn connect(&mut self, ip: IpAddr) -> AppResult<()> {
+        self.attempts.retain(|_, attempts| {
+            attempts
+                .last()
+                .is_some_and(|attempt| attempt.elapsed() < Self::RATE_WINDOW)
+        });
+        let attempts = self.attempts.entry(ip).or_default();
+        attempts.retain(|attempt| attempt.elapsed() < Self::RATE_WINDOW);
+        attempts.push(Instant::now());
+        if attempts.len() > self.max_connections_per_minute {
+            return Err(anyhow!("Too many connections, try again in a minute"));
+        }
+
+        let open = self.open.entry(ip).or_default();
+        if *open >= self.max_connections_per_ip {
+            return Err(anyhow!("Too many connections from your address"));
+        }
+        *open += 1;
+        Ok(())
+    }
+
+    fn disconnect(&mut self, ip: IpAddr) {
+        if let Some(open) = self.open.get_mut(&ip) {
+            *open = open.saturating_sub(1);
+            if *open == 0 {
+                self.open.remove(&ip);
+            }
+        }
+    }
+}
+
+// Counts as an open connection until dropped along with its client.
+#[derive(Debug)]
+pub struct ConnectionGuard {
+    limiter: Arc<Mutex<ConnectionLimiter>>,
+    ip: IpAddr,
+}
+
+impl ConnectionGuard {
+    pub fn new(limiter: Arc<Mutex<ConnectionLimiter>>, ip: IpAddr) -> AppResult<Self> {
+        limiter
+            .lock()
+            .map_err(|e| anyhow!("Connection limiter lock poisoned: {}", e))?
+            .connect(ip)?;
+        Ok(Self { limiter, ip })
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        if let Ok(mut limiter) = self.limiter.lock() {
+            limiter.disconnect(self.ip);
+        }
+    }
+}
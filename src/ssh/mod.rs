@@ -1,9 +1,10 @@
 mod channel;
 mod client;
+mod limiter;
 mod server;
 mod ssh_event_handler;
 mod utils;
 
 pub use crate::ssh::channel::SSHWriterProxy;
-pub use crate::ssh::server::AppServer;
+pub use crate::ssh::server::{AppServer, ServerOptions};
 pub use crate::ssh::ssh_event_handler::{SSHEventHandler, TerminalEvent};
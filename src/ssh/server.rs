@@ -1,26 +1,32 @@
 use super::client::{AppClient, PlayerId};
-use crate::game::{Game, HeroCommand};
+use super::limiter::{ConnectionGuard, ConnectionLimiter};
+use crate::accounts::AccountStore;
+use crate::game::{Game, GameConfig, GameInstance, GameStores, HeroCommand, ReplayChunk};
 use crate::ssh::TerminalEvent;
 use crate::tui::Tui;
-use crate::AppResult;
+use crate::{load_store, AppResult};
 use crossterm::event::KeyCode;
-use itertools::Either;
+use itertools::{Either, Itertools};
+use rand::Rng;
+use russh::keys::Algorithm;
 use russh::server::{self};
 use russh::server::{Config, Server};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fs::File;
 use std::io::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
 use std::pin::pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc::Sender;
 use tokio::sync::mpsc::{self, Receiver};
-use tokio::task;
-use tokio::{select, time};
+use tokio::task::{self, JoinHandle};
+use tokio::{select, signal};
 use tokio_util::sync::CancellationToken;
 
-fn save_keys(signing_key: &russh::keys::PrivateKey) -> AppResult<()> {
-    let file = File::create::<&str>("./keys".into())?;
+fn save_keys(path: &Path, signing_key: &russh::keys::PrivateKey) -> AppResult<()> {
+    let file = File::create(path)?;
     assert!(file.metadata()?.is_file());
     let mut buffer = std::io::BufWriter::new(file);
     buffer.write(&signing_key.to_bytes()?)?;
@@ -28,155 +34,575 @@ fn save_keys(signing_key: &russh::keys::PrivateKey) -> AppResult<()> {
     Ok(())
 }
 
-fn load_keys() -> AppResult<russh::keys::PrivateKey> {
-    let bytes = std::fs::read("./keys")?;
+fn load_keys(path: &Path) -> AppResult<russh::keys::PrivateKey> {
+    let bytes = std::fs::read(path)?;
     let private_key = russh::keys::PrivateKey::from_bytes(&bytes)?;
     println!("Loaded keypair for SSH server.");
     Ok(private_key)
 }
 
+// Deployment settings, given on the command line.
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    pub address: IpAddr,
+    pub port: u16,
+    pub host_key_path: PathBuf,
+    // Used when generating a new host key.
+    pub key_algorithm: Algorithm,
+    // Heros playing at once, spectators excluded.
+    pub max_players: Option<usize>,
+    pub max_connections_per_ip: usize,
+    pub max_connections_per_minute: usize,
+    // Seed of the public game rooms.
+    pub seed: Option<u64>,
+}
+
 pub struct AppServer {
-    port: u16,
+    options: ServerOptions,
+    config: GameConfig,
     shutdown: CancellationToken,
+    // Cancelled on SIGINT/SIGTERM, starting the restart countdown.
+    shutdown_requested: CancellationToken,
+    account_store: Arc<Mutex<AccountStore>>,
+    connection_limiter: Arc<Mutex<ConnectionLimiter>>,
     client_sender: Option<Sender<Tui>>,
     terminal_event_sender: Option<Sender<(PlayerId, TerminalEvent)>>,
 }
 
 impl AppServer {
-    const INACTIVITY_TIMEOUT: Duration = Duration::from_secs(60);
-    pub fn new(port: u16) -> Self {
+    const SHUTDOWN_COUNTDOWN: Duration = Duration::from_secs(10);
+
+    pub fn new(options: ServerOptions, config: GameConfig) -> Self {
+        let connection_limiter = ConnectionLimiter::new(
+            options.max_connections_per_ip,
+            options.max_connections_per_minute,
+        );
         Self {
-            port,
+            options,
+            config,
             shutdown: CancellationToken::new(),
+            shutdown_requested: CancellationToken::new(),
+            account_store: Arc::new(Mutex::new(AccountStore::default())),
+            connection_limiter: Arc::new(Mutex::new(connection_limiter)),
             client_sender: None,
             terminal_event_sender: None,
         }
     }
 
     pub async fn run(&mut self) -> AppResult<()> {
-        println!(
-            "Starting SSH server on port {}. Press Ctrl-C to exit.",
-            self.port
-        );
+        let address = SocketAddr::new(self.options.address, self.options.port);
+        println!("Starting SSH server on {}. Press Ctrl-C to exit.", address);
 
-        let private_key = load_keys().unwrap_or_else(|_| {
+        let host_key_path = &self.options.host_key_path;
+        let private_key = load_keys(host_key_path).unwrap_or_else(|_| {
             let key = russh::keys::PrivateKey::random(
                 &mut rand::thread_rng(),
-                russh::keys::Algorithm::Ed25519,
+                self.options.key_algorithm.clone(),
             )
             .expect("Failed to generate SSH keys.");
 
-            save_keys(&key).expect("Failed to save SSH keys.");
+            save_keys(host_key_path, &key).expect("Failed to save SSH keys.");
             key
         });
+        if private_key.algorithm() != self.options.key_algorithm {
+            log::warn!(
+                "Using existing {} host key from {}.",
+                private_key.algorithm(),
+                host_key_path.display()
+            );
+        }
+
+        let (mut account_store, can_save) =
+            load_store("accounts", AccountStore::load, AccountStore::backup);
+        if !can_save {
+            account_store.disable_saving();
+        }
+        self.account_store = Arc::new(Mutex::new(account_store));
 
+        // Sessions sending and receiving nothing, like ones that never open a terminal,
+        // are closed as idle as heros who do not move.
         let config = Config {
-            inactivity_timeout: Some(std::time::Duration::from_secs(120)),
+            inactivity_timeout: Some(self.config.inactivity_timeout()),
             auth_rejection_time: std::time::Duration::from_secs(3),
             auth_rejection_time_initial: Some(std::time::Duration::from_secs(0)),
             keys: vec![private_key],
             ..Default::default()
         };
 
-        let shutdown = self.shutdown.clone();
-
         let (client_sender, client_receiver) = mpsc::channel(1);
         self.client_sender = Some(client_sender);
 
         let (terminal_event_sender, terminal_event_receiver) = mpsc::channel(1);
         self.terminal_event_sender = Some(terminal_event_sender);
 
-        Self::spawn_game(
+        let game_handle = Self::spawn_game(
+            self.options.clone(),
+            self.config,
             client_receiver,
             terminal_event_receiver,
-            self.shutdown.clone(),
+            self.shutdown_requested.clone(),
         );
 
-        let server = self.run_on_address(Arc::new(config), ("0.0.0.0", self.port));
-
-        let shutdown_cancelled = shutdown.cancelled();
-
+        // Dropping the server future stops accepting new clients,
+        // while the sessions of connected ones keep running.
         let result = {
+            let server = self.run_on_address(Arc::new(config), address);
             let mut server = pin!(server);
-            let mut shutdown_cancelled = pin!(shutdown_cancelled);
             select! {
                 result = &mut server => Either::Left(result),
-                _ = &mut shutdown_cancelled => Either::Right(()),
+                _ = Self::shutdown_signal() => Either::Right(()),
             }
         };
 
         match result {
             Either::Left(result) => Ok(result?),
             Either::Right(_) => {
-                println!("Shutting down");
-                time::sleep(Duration::from_secs(1)).await;
+                log::info!(
+                    "Shutting down in {} seconds",
+                    Self::SHUTDOWN_COUNTDOWN.as_secs()
+                );
+                self.shutdown_requested.cancel();
+                // The game task restores the terminals and flushes everything to disk.
+                if let Err(e) = game_handle.await {
+                    log::error!("Error stopping game: {}", e);
+                }
+                self.shutdown.cancel();
 
                 Ok(())
             }
         }
     }
 
+    async fn shutdown_signal() {
+        #[cfg(unix)]
+        let terminate = async {
+            match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+                Ok(mut sigterm) => {
+                    sigterm.recv().await;
+                }
+                Err(e) => {
+                    log::error!("Could not listen for SIGTERM: {}", e);
+                    std::future::pending::<()>().await;
+                }
+            }
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+
+        select! {
+            result = signal::ctrl_c() => {
+                if let Err(e) = result {
+                    log::error!("Could not listen for Ctrl-C: {}", e);
+                    std::future::pending::<()>().await;
+                }
+            }
+            _ = terminate => {}
+        }
+    }
+
+    // Removes the client from its game.
+    async fn disconnect(
+        games: &mut HashMap<GameInstance, Game>,
+        tuis: &mut HashMap<PlayerId, Tui>,
+        player_id: PlayerId,
+    ) {
+        let Some(mut tui) = tuis.remove(&player_id) else {
+            return;
+        };
+        let _ = tui.exit().await;
+
+        let Some(instance) = tui.instance() else {
+            return;
+        };
+
+        if let Some(game) = games.get_mut(instance) {
+            game.remove_player(&player_id);
+        }
+    }
+
+    // Removes the client, but keeps the hero of registered players so that they can reconnect.
+    async fn detach(
+        games: &mut HashMap<GameInstance, Game>,
+        tuis: &mut HashMap<PlayerId, Tui>,
+        player_id: PlayerId,
+    ) {
+        let Some(tui) = tuis.get(&player_id) else {
+            return;
+        };
+
+        let is_detached = tui.is_registered()
+            && !tui.is_spectator()
+            && tui
+                .instance()
+                .and_then(|instance| games.get_mut(instance))
+                .is_some_and(|game| game.detach_player(&player_id));
+        if !is_detached {
+            Self::disconnect(games, tuis, player_id).await;
+            return;
+        }
+
+        if let Some(mut tui) = tuis.remove(&player_id) {
+            let _ = tui.exit().await;
+        }
+    }
+
+    // Private games are closed once everyone left and no hero waits for its player.
+    fn close_if_empty(
+        games: &mut HashMap<GameInstance, Game>,
+        tuis: &HashMap<PlayerId, Tui>,
+        instance: &GameInstance,
+        replay_writer: &ReplayWriter,
+    ) {
+        if *instance == GameInstance::Public
+            || tuis.values().any(|tui| tui.instance() == Some(instance))
+            || games
+                .get(instance)
+                .is_some_and(|game| game.has_detached_players())
+        {
+            return;
+        }
+
+        if let Some(mut game) = games.remove(instance) {
+            game.finish_replays();
+            replay_writer.write(&mut game);
+        }
+    }
+
+    fn is_full(games: &HashMap<GameInstance, Game>, max_players: Option<usize>) -> bool {
+        let number_of_players: usize = games.values().map(|game| game.number_of_players()).sum();
+        max_players.is_some_and(|max| number_of_players >= max)
+    }
+
+    // Players wait in the queue while the server is full, or while others are already waiting.
+    fn join_game(
+        games: &mut HashMap<GameInstance, Game>,
+        tui: &mut Tui,
+        instance: GameInstance,
+        max_players: Option<usize>,
+        queue: &mut VecDeque<(PlayerId, GameInstance)>,
+    ) {
+        if !tui.is_spectator() && (Self::is_full(games, max_players) || !queue.is_empty()) {
+            queue.push_back((tui.id, instance));
+            tui.set_queue_position(Some(queue.len()));
+            return;
+        }
+
+        Self::enter_game(games, tui, instance);
+    }
+
+    fn enter_game(games: &mut HashMap<GameInstance, Game>, tui: &mut Tui, instance: GameInstance) {
+        let Some(game) = games.get_mut(&instance) else {
+            tui.set_queue_position(None);
+            tui.set_lobby_message("The labyrinth is gone".to_string());
+            return;
+        };
+
+        if !tui.is_spectator() {
+            game.add_player(tui.id, tui.username(), tui.is_registered());
+        }
+        tui.join(instance);
+    }
+
+    // Queued players join in order as soon as there is room.
+    fn process_queue(
+        games: &mut HashMap<GameInstance, Game>,
+        tuis: &mut HashMap<PlayerId, Tui>,
+        queue: &mut VecDeque<(PlayerId, GameInstance)>,
+        max_players: Option<usize>,
+    ) {
+        queue.retain(|(player_id, _)| tuis.get(player_id).is_some_and(|tui| tui.is_queued()));
+        while !Self::is_full(games, max_players) {
+            let Some((player_id, instance)) = queue.pop_front() else {
+                break;
+            };
+            if let Some(tui) = tuis.get_mut(&player_id) {
+                Self::enter_game(games, tui, instance);
+            }
+        }
+
+        for (index, (player_id, _)) in queue.iter().enumerate() {
+            if let Some(tui) = tuis.get_mut(player_id) {
+                tui.set_queue_position(Some(index + 1));
+            }
+        }
+    }
+
+    fn random_invite_code(games: &HashMap<GameInstance, Game>) -> String {
+        let rng = &mut rand::thread_rng();
+        loop {
+            let invite_code = (0..Tui::INVITE_CODE_LENGTH)
+                .map(|_| char::from(b'0' + rng.gen_range(0..10)))
+                .collect::<String>();
+            let instance = GameInstance::Private {
+                invite_code: invite_code.clone(),
+            };
+            if !games.contains_key(&instance) {
+                return invite_code;
+            }
+        }
+    }
+
     fn spawn_game(
+        options: ServerOptions,
+        config: GameConfig,
         mut client_receiver: Receiver<Tui>,
         mut terminal_event_receiver: Receiver<(PlayerId, TerminalEvent)>,
-        server_shutdown: CancellationToken,
-    ) {
+        shutdown_requested: CancellationToken,
+    ) -> JoinHandle<()> {
         task::spawn(async move {
-            let mut game = Game::new();
+            let public_game = match options.seed {
+                Some(seed) => Game::with_seed(seed, config, GameStores::load()),
+                None => Game::new(config, GameStores::load()),
+            };
+            let mut games = HashMap::from([(GameInstance::Public, public_game)]);
             let mut update_ticker = tokio::time::interval(Game::update_time_step());
             let mut draw_ticker = tokio::time::interval(Game::draw_time_step());
+            let mut save_ticker = tokio::time::interval(Game::save_time_step());
 
             let mut tuis: HashMap<PlayerId, Tui> = HashMap::new();
             let mut last_moves: HashMap<PlayerId, Instant> = HashMap::new();
+            let mut shutdown_at: Option<Instant> = None;
+            let mut queue: VecDeque<(PlayerId, GameInstance)> = VecDeque::new();
+            let replay_writer = ReplayWriter::spawn();
+            let mut store_saves: Vec<JoinHandle<()>> = vec![];
 
             loop {
                 select! {
-                    Some(tui) = client_receiver.recv() => {
-                        game.add_player(tui.id,tui.username());
-                        last_moves.insert(tui.id, Instant::now());
+                    Some(mut tui) = client_receiver.recv() => {
+                        // A new connection to the same account replaces the old one, and takes over its hero.
+                        Self::detach(&mut games, &mut tuis, tui.id).await;
+
+                        // Spectators are never kicked for inactivity.
+                        if !tui.is_spectator() {
+                            last_moves.insert(tui.id, Instant::now());
+                        }
+                        // Clients start in the lobby, unless their hero is waiting for them.
+                        let waiting_instance = games
+                            .iter_mut()
+                            .find_map(|(instance, game)| game.reattach_player(&tui.id).then(|| instance.clone()));
+                        if let Some(instance) = waiting_instance {
+                            log::info!("{} reconnected", tui.username());
+                            tui.join(instance);
+                        }
                         tuis.insert(tui.id, tui);
                     }
 
                     _ = update_ticker.tick() => {
-                        game.update();
+                        for game in games.values_mut() {
+                            game.update();
+                            replay_writer.write(game);
+                        }
+                        // Players may have left, and detached heros may have run out of time.
+                        let instances = games.keys().cloned().collect_vec();
+                        for instance in instances.iter() {
+                            Self::close_if_empty(&mut games, &tuis, instance, &replay_writer);
+                        }
+                    }
+
+                    // Only the public game is persisted.
+                    _ = save_ticker.tick() => {
+                        let game = games.get_mut(&GameInstance::Public).expect("Public game should exist");
+                        let hall_of_fame = game.hall_of_fame();
+                        let stats_store = game.stats_store();
+                        let ghost_store = game.ghost_store();
+                        store_saves.retain(|handle| !handle.is_finished());
+                        store_saves.push(task::spawn_blocking(move || {
+                            if let Some(Err(e)) = hall_of_fame.map(|hall_of_fame| hall_of_fame.save()) {
+                                log::error!("Error saving hall of fame: {}", e);
+                            }
+                            if let Some(Err(e)) = stats_store.map(|stats_store| stats_store.save()) {
+                                log::error!("Error saving stats: {}", e);
+                            }
+                            if let Some(Err(e)) = ghost_store.map(|ghost_store| ghost_store.save()) {
+                                log::error!("Error saving ghosts: {}", e);
+                            }
+                        }));
                     }
 
                     _ = draw_ticker.tick() => {
+                        Self::process_queue(&mut games, &mut tuis, &mut queue, options.max_players);
+
+                        if let Some(shutdown_at) = shutdown_at {
+                            let remaining = shutdown_at.saturating_duration_since(Instant::now());
+                            if remaining.is_zero() {
+                                break;
+                            }
+                            let banner = format!(
+                                "Server restarting in {} seconds",
+                                remaining.as_secs_f64().ceil()
+                            );
+                            for tui in tuis.values_mut() {
+                                tui.set_banner(banner.clone());
+                            }
+                        }
+
                         let mut to_remove = vec![];
+                        let mut to_detach = vec![];
+                        let (public_players, private_players) = games.iter().fold((0, 0), |(public, private), (instance, game)| {
+                            if *instance == GameInstance::Public {
+                                (public + game.number_of_players(), private)
+                            } else {
+                                (public, private + game.number_of_players())
+                            }
+                        });
                         for (&player_id, tui) in tuis.iter_mut() {
-                            tui.draw(&game).expect("Can't draw tui");
+                            let drawn = match tui.instance().and_then(|instance| games.get(instance)) {
+                                Some(game) => tui.draw(game),
+                                None => tui.draw_lobby(public_players, private_players),
+                            };
+                            // A broken widget only affects its own client.
+                            if let Err(e) = drawn {
+                                log::error!("Error drawing tui: {}", e);
+                            }
+
                             if let Err(e) = tui.push_data().await {
-                                println!("Error pushing to tui: {}", e);
-                                let _ = tui.exit().await;
-                                to_remove.push(player_id);
+                                log::error!("Error pushing to tui: {}", e);
+                                to_detach.push(player_id);
                             } else if let Some(last_move) = last_moves.get(&player_id) {
-                                if last_move.elapsed() > Self::INACTIVITY_TIMEOUT {
-                                    let _ = tui.exit().await;
+                                if last_move.elapsed() > config.inactivity_timeout() {
                                     to_remove.push(player_id);
                                 }
                             }
                         }
                         for player_id in to_remove {
-                            game.remove_player(&player_id);
-                            tuis.remove(&player_id);
+                            Self::disconnect(&mut games, &mut tuis, player_id).await;
+                            last_moves.remove(&player_id);
+                        }
+                        for player_id in to_detach {
+                            Self::detach(&mut games, &mut tuis, player_id).await;
+                            last_moves.remove(&player_id);
                         }
                     }
 
                     Some((player_id, event)) = terminal_event_receiver.recv() => {
-                        last_moves.insert(player_id, Instant::now());
+                        let Some(tui) = tuis.get_mut(&player_id) else {
+                            continue;
+                        };
+
+                        if !tui.is_spectator() {
+                            last_moves.insert(player_id, Instant::now());
+                        }
+
+                        let instance = tui.instance().cloned();
                         match event {
-                            TerminalEvent::Key{key_event} => {
+                            TerminalEvent::Key{key_event} if instance.is_none() && tui.is_queued() => {
+                                if matches!(key_event.code, KeyCode::Char('q') | KeyCode::Esc) {
+                                    Self::disconnect(&mut games, &mut tuis, player_id).await;
+                                    last_moves.remove(&player_id);
+                                }
+                            }
+
+                            TerminalEvent::Key{key_event} if instance.is_none() => {
                                 match key_event.code {
                                     KeyCode::Char('q') | KeyCode::Esc => {
-                                        game.remove_player(&player_id);
+                                        Self::disconnect(&mut games, &mut tuis, player_id).await;
+                                        last_moves.remove(&player_id);
+                                    }
+                                    KeyCode::Char(c) if c.is_ascii_digit() => {
+                                        tui.push_invite_code_digit(c);
+                                    }
+                                    KeyCode::Backspace => tui.pop_invite_code_digit(),
+                                    KeyCode::Char(c @ ('b' | 's' | 'g')) if !tui.is_spectator() => tui.toggle_interaction(c),
+                                    KeyCode::Char('n' | 'r' | 't') if tui.is_spectator() => {
+                                        tui.set_lobby_message("Spectators cannot create labyrinths".to_string());
+                                    }
+                                    KeyCode::Char('n' | 'r' | 't') if Self::is_full(&games, options.max_players) => {
+                                        tui.set_lobby_message("The server is full, try again later".to_string());
+                                    }
+                                    KeyCode::Char(c @ ('n' | 'r' | 't')) => {
+                                        let invite_code = Self::random_invite_code(&games);
+                                        let instance = GameInstance::Private {
+                                            invite_code: invite_code.clone(),
+                                        };
+                                        let mut game = match c {
+                                            'r' => Game::new_race(invite_code, config),
+                                            't' => Game::new_coop(invite_code, config),
+                                            _ => Game::new_private(invite_code, config),
+                                        };
+                                        game.set_interactions(tui.interactions());
+                                        games.insert(instance.clone(), game);
+                                        Self::join_game(&mut games, tui, instance, options.max_players, &mut queue);
+                                    }
+                                    KeyCode::Enter => {
+                                        let instance = if tui.invite_code().is_empty() {
+                                            GameInstance::Public
+                                        } else {
+                                            GameInstance::Private {
+                                                invite_code: tui.invite_code().to_string(),
+                                            }
+                                        };
+
+                                        if games.contains_key(&instance) {
+                                            Self::join_game(&mut games, tui, instance, options.max_players, &mut queue);
+                                        } else {
+                                            let message = format!(
+                                                "No labyrinth with invite code {}",
+                                                tui.invite_code()
+                                            );
+                                            tui.set_lobby_message(message);
+                                        }
+                                    }
+                                    _ => {}
+                                }
+                            }
 
-                                        if let Some(tui) = tuis.get_mut(&player_id) {
-                                            let _ = tui.exit().await;
+                            TerminalEvent::Key{key_event} => {
+                                let Some(game) = instance
+                                    .as_ref()
+                                    .and_then(|instance| games.get_mut(instance))
+                                else {
+                                    continue;
+                                };
+
+                                // While chatting, keys edit the message.
+                                if tui.is_chatting() {
+                                    match key_event.code {
+                                        KeyCode::Esc => tui.cancel_chat(),
+                                        KeyCode::Tab => tui.cycle_chat_channel(),
+                                        KeyCode::Backspace => tui.pop_chat_char(),
+                                        KeyCode::Char(c) => tui.push_chat_char(c),
+                                        KeyCode::Enter => {
+                                            if let Some((channel, text)) = tui.take_chat_message() {
+                                                if let Err(err) = game.send_chat(&player_id, channel, &text) {
+                                                    tui.set_chat_notice(text, err.to_string());
+                                                }
+                                            }
                                         }
-                                        tuis.remove(&player_id);
+                                        _ => {}
+                                    }
+                                    continue;
+                                }
+
+                                match key_event.code {
+                                    KeyCode::Char('q') | KeyCode::Esc => {
+                                        Self::disconnect(&mut games, &mut tuis, player_id).await;
+                                        last_moves.remove(&player_id);
+                                    }
+
+                                    KeyCode::Left | KeyCode::Right if tui.is_spectator() => {
+                                        tui.cycle_spectator_view(game, key_event.code == KeyCode::Right);
+                                    }
+
+                                    KeyCode::Char('v') if tui.is_spectator() => {
+                                        tui.toggle_spectator_view(game);
+                                    }
+
+                                    KeyCode::Char('s') if !tui.is_spectator() => {
+                                        tui.toggle_stats_screen();
+                                    }
+
+                                    KeyCode::Enter if !tui.is_spectator() => {
+                                        game.start_race();
+                                    }
+
+                                    KeyCode::Char('t') if !tui.is_spectator() => {
+                                        tui.start_chat();
                                     }
 
                                     code => {
+                                        if tui.is_spectator() {
+                                            continue;
+                                        }
                                         if let Some(command) = HeroCommand::from_key_code(code) {
                                             game.handle_command(&command, player_id);
                                         }
@@ -184,33 +610,103 @@ impl AppServer {
                                 }
                             }
 
-                            TerminalEvent::Resize{width, height} => {
-                                if let Some(tui) = tuis.get_mut(&player_id).as_mut() {
-                                    let _ = tui.resize(width, height);
+                            // Pasted text only goes to the chat and to the invite code, never to the hero.
+                            TerminalEvent::Paste{text} => {
+                                if tui.is_chatting() {
+                                    for c in text.chars().filter(|c| !c.is_control()) {
+                                        tui.push_chat_char(c);
+                                    }
+                                } else if instance.is_none() && !tui.is_queued() {
+                                    for c in text.chars().filter(char::is_ascii_digit) {
+                                        tui.push_invite_code_digit(c);
+                                    }
                                 }
+                            }
+
+                            TerminalEvent::Resize{width, height} => {
+                                let _ = tui.resize(width, height);
                             },
                             _ => {}
                         }
                     }
 
-                    _ = server_shutdown.cancelled() => {
-                        break
+                    _ = shutdown_requested.cancelled(), if shutdown_at.is_none() => {
+                        shutdown_at = Some(Instant::now() + Self::SHUTDOWN_COUNTDOWN);
                     }
 
                 }
             }
 
-            // Game has ended.
+            // Server is shutting down.
             for tui in tuis.values_mut() {
                 let _ = tui.exit().await;
             }
+
+            // Saves still running would otherwise overwrite the final ones.
+            for handle in store_saves {
+                if let Err(e) = handle.await {
+                    log::error!("Error saving stores: {}", e);
+                }
+            }
+
+            if let Some(game) = games.get_mut(&GameInstance::Public) {
+                if let Some(Err(e)) = game.hall_of_fame().map(|hall_of_fame| hall_of_fame.save()) {
+                    log::error!("Error saving hall of fame: {}", e);
+                }
+                if let Some(Err(e)) = game.stats_store().map(|stats_store| stats_store.save()) {
+                    log::error!("Error saving stats: {}", e);
+                }
+                if let Some(Err(e)) = game.ghost_store().map(|ghost_store| ghost_store.save()) {
+                    log::error!("Error saving ghosts: {}", e);
+                }
+            }
+
+            for game in games.values_mut() {
+                game.finish_replays();
+                replay_writer.write(game);
+            }
+            replay_writer.close().await;
+        })
+    }
+}
+
+// Writes replays on a blocking thread, in the order they were recorded.
+struct ReplayWriter {
+    sender: std::sync::mpsc::Sender<ReplayChunk>,
+    handle: JoinHandle<()>,
+}
+
+impl ReplayWriter {
+    fn spawn() -> Self {
+        let (sender, receiver) = std::sync::mpsc::channel::<ReplayChunk>();
+        let handle = task::spawn_blocking(move || {
+            for chunk in receiver {
+                if let Err(e) = chunk.write() {
+                    log::error!("Error saving replay: {}", e);
+                }
+            }
         });
+        Self { sender, handle }
+    }
+
+    fn write(&self, game: &mut Game) {
+        for chunk in game.take_replay_chunks() {
+            let _ = self.sender.send(chunk);
+        }
+    }
+
+    // Waits for the queued replays to be written.
+    async fn close(self) {
+        drop(self.sender);
+        if let Err(e) = self.handle.await {
+            log::error!("Error saving replays: {}", e);
+        }
     }
 }
 
 impl server::Server for AppServer {
     type Handler = AppClient;
-    fn new_client(&mut self, _peer_addr: Option<std::net::SocketAddr>) -> AppClient {
+    fn new_client(&mut self, peer_addr: Option<std::net::SocketAddr>) -> AppClient {
         let client_sender = self
             .client_sender
             .as_ref()
@@ -222,7 +718,22 @@ impl server::Server for AppServer {
             .as_ref()
             .expect("Tui sender should have been initialized")
             .clone();
-        let client = AppClient::new(self.shutdown.clone(), client_sender, terminal_event_sender);
+        let mut client = AppClient::new(
+            self.shutdown.clone(),
+            self.account_store.clone(),
+            client_sender,
+            terminal_event_sender,
+        );
+
+        if let Some(peer_addr) = peer_addr {
+            match ConnectionGuard::new(self.connection_limiter.clone(), peer_addr.ip()) {
+                Ok(connection) => client.track_connection(connection),
+                Err(e) => {
+                    log::warn!("Rejected connection from {}: {}", peer_addr, e);
+                    client.reject(e.to_string());
+                }
+            }
+        }
 
         client
     }
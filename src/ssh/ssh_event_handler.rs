@@ -1,18 +1,21 @@
-use crate::ssh::utils::convert_data_to_crossterm_event;
-use crossterm::event::{Event as CrosstermEvent, KeyCode, KeyEvent, KeyEventKind, MouseEvent};
+use crate::game::Game;
+use crate::ssh::utils::InputParser;
+use crossterm::event::{Event as CrosstermEvent, KeyEvent, KeyEventKind};
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use tokio::select;
 use tokio::sync::mpsc;
+use tokio::time;
 use tokio_util::sync::CancellationToken;
 
 use super::client::PlayerId;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum TerminalEvent {
     Key { key_event: KeyEvent },
-    Mouse { mouse_event: MouseEvent },
+    // Text pasted in a terminal with bracketed paste.
+    Paste { text: String },
     Resize { width: u16, height: u16 },
     Quit,
 }
@@ -20,7 +23,7 @@ pub enum TerminalEvent {
 impl Future for TerminalEvent {
     type Output = Self;
     fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
-        Poll::Ready(*self)
+        Poll::Ready(self.clone())
     }
 }
 
@@ -29,8 +32,6 @@ impl Future for TerminalEvent {
 pub struct SSHEventHandler {}
 
 impl SSHEventHandler {
-    pub const CMD_RESIZE: u8 = 0x04;
-
     pub fn start(
         mut stdin: mpsc::Receiver<Vec<u8>>,
         terminal_event_sender: mpsc::Sender<(PlayerId, TerminalEvent)>,
@@ -40,37 +41,29 @@ impl SSHEventHandler {
     ) {
         {
             tokio::task::spawn(async move {
+                let mut parser = InputParser::default();
+                // An Esc waiting for the rest of a sequence is sent as a key on
+                // the game tick, once the sequence has timed out.
+                let mut ticker = time::interval(Game::update_time_step());
                 loop {
                     select! {
                         Some(stdin) = stdin.recv() => {
-                            if let Some(event) = convert_data_to_crossterm_event(&stdin) {
-                                match event {
-                                    CrosstermEvent::Key(key_event) => {
-                                        if key_event.kind == KeyEventKind::Press {
-                                            terminal_event_sender.send((player_id,TerminalEvent::Key{key_event})).await.expect("Cannot send over channel");
-
-                                            if key_event.code == KeyCode::Esc || key_event.code == KeyCode::Char('q') {
-                                                client_shutdown.cancel();
-                                                break;
-                                            }
-
-                                        }
-                                    }
-                                    CrosstermEvent::Resize(w, h) =>
-                                    {
-                                        terminal_event_sender.send((player_id, TerminalEvent::Resize{width:w, height:h})).await.expect("Cannot send over channel");
-                                    }
-                                    _ => {}
-                                };
+                            // Quitting is handled by the server, which knows whether the
+                            // hero is chatting, and then cancels the client.
+                            Self::send(parser.parse(&stdin), &terminal_event_sender, player_id).await;
+                        }
+                        _ = ticker.tick(), if parser.is_pending() => {
+                            if parser.is_timed_out() {
+                                Self::send(parser.flush(), &terminal_event_sender, player_id).await;
                             }
                         }
                         _ = client_shutdown.cancelled() => {
-                                println!("Shutting down client.");
+                                log::debug!("Shutting down client.");
                                 break;
                         },
 
                         _ = server_shutdown.cancelled() => {
-                            println!("Shutting down from server.");
+                            log::debug!("Shutting down from server.");
                             break;
                     },
 
@@ -80,4 +73,24 @@ impl SSHEventHandler {
             })
         };
     }
+
+    async fn send(
+        events: Vec<CrosstermEvent>,
+        terminal_event_sender: &mpsc::Sender<(PlayerId, TerminalEvent)>,
+        player_id: PlayerId,
+    ) {
+        for event in events {
+            let event = match event {
+                CrosstermEvent::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                    TerminalEvent::Key { key_event }
+                }
+                CrosstermEvent::Paste(text) => TerminalEvent::Paste { text },
+                _ => continue,
+            };
+            terminal_event_sender
+                .send((player_id, event))
+                .await
+                .expect("Cannot send over channel");
+        }
+    }
 }
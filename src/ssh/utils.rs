@@ -1,119 +1,399 @@
-use super::SSHEventHandler;
-use crate::AppResult;
-use anyhow::anyhow;
-use crossterm::event::KeyModifiers;
-
-fn convert_data_to_key_event(data: &[u8]) -> Option<crossterm::event::KeyEvent> {
-    let key = match data {
-        b"\x1b\x5b\x41" => crossterm::event::KeyCode::Up,
-        b"\x1b\x5b\x42" => crossterm::event::KeyCode::Down,
-        b"\x1b\x5b\x43" => crossterm::event::KeyCode::Right,
-        b"\x1b\x5b\x44" => crossterm::event::KeyCode::Left,
-        b"\x03" | b"\x1b" => crossterm::event::KeyCode::Esc, // Ctrl-C is also sent as Esc
-        b"\x0d" => crossterm::event::KeyCode::Enter,
-        b"\x7f" => crossterm::event::KeyCode::Backspace,
-        b"\x1b[3~" => crossterm::event::KeyCode::Delete,
-        b"\x09" => crossterm::event::KeyCode::Tab,
-        x if x.len() == 1 => crossterm::event::KeyCode::Char(data[0] as char),
-        _ => {
-            return None;
-        }
-    };
-    let event = crossterm::event::KeyEvent::new(key, crossterm::event::KeyModifiers::empty());
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use std::time::{Duration, Instant};
 
-    Some(event)
+enum Parsed {
+    Event(Event, usize),
+    // Bytes of an unknown or invalid sequence, dropped without an event.
+    Skip(usize),
+    Incomplete,
 }
 
-fn decode_sgr_mouse_input(ansi_code: Vec<u8>) -> AppResult<(u8, u16, u16)> {
-    // Convert u8 vector to a String
-    let ansi_str =
-        String::from_utf8(ansi_code.clone()).map_err(|_| anyhow!("Invalid UTF-8 sequence"))?;
+fn key(code: KeyCode, modifiers: KeyModifiers, length: usize) -> Parsed {
+    Parsed::Event(Event::Key(KeyEvent::new(code, modifiers)), length)
+}
 
-    // Check the prefix
-    if !ansi_str.starts_with("\x1b[<") {
-        return Err(anyhow!("Invalid SGR ANSI mouse code"));
+// xterm encodes modifiers as 1 + a bitmask of shift, alt and control.
+fn decode_modifiers(param: u16) -> KeyModifiers {
+    let mask = param.saturating_sub(1);
+    let mut modifiers = KeyModifiers::empty();
+    if mask & 1 != 0 {
+        modifiers |= KeyModifiers::SHIFT;
+    }
+    if mask & 2 != 0 {
+        modifiers |= KeyModifiers::ALT;
     }
+    if mask & 4 != 0 {
+        modifiers |= KeyModifiers::CONTROL;
+    }
+    modifiers
+}
 
-    let cb_mod = if ansi_str.ends_with('M') {
-        0
-    } else if ansi_str.ends_with('m') {
-        3
-    } else {
-        return Err(anyhow!("Invalid SGR ANSI mouse code"));
+// Plain characters and control keys.
+fn parse_char(data: &[u8]) -> Parsed {
+    let (code, modifiers) = match data[0] {
+        b'\r' | b'\n' => (KeyCode::Enter, KeyModifiers::empty()),
+        b'\t' => (KeyCode::Tab, KeyModifiers::empty()),
+        0x7f | 0x08 => (KeyCode::Backspace, KeyModifiers::empty()),
+        0x03 => (KeyCode::Esc, KeyModifiers::empty()), // Ctrl-C is also sent as Esc
+        0x00 => (KeyCode::Char(' '), KeyModifiers::CONTROL),
+        c @ 0x01..=0x1a => (KeyCode::Char((b'a' + c - 1) as char), KeyModifiers::CONTROL),
+        c @ 0x1c..=0x1f => (
+            KeyCode::Char((b'4' + c - 0x1c) as char),
+            KeyModifiers::CONTROL,
+        ),
+        _ => return parse_utf8(data),
     };
+    key(code, modifiers, 1)
+}
 
-    // Remove the prefix '\x1b[<' and trailing 'M'
-    let code_body = &ansi_str[3..ansi_str.len() - 1];
-
-    // Split the components
-    let components: Vec<&str> = code_body.split(';').collect();
-
-    if components.len() != 3 {
-        return Err(anyhow!("Invalid SGR ANSI mouse code format"));
+fn parse_utf8(data: &[u8]) -> Parsed {
+    let length = match data[0] {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return Parsed::Skip(1),
+    };
+    if data.len() < length {
+        return Parsed::Incomplete;
     }
 
-    // Parse the components
-    let cb = cb_mod
-        + components[0]
-            .parse::<u8>()
-            .map_err(|_| anyhow!("Failed to parse Cb"))?;
-    let cx = components[1]
-        .parse::<u16>()
-        .map_err(|_| anyhow!("Failed to parse Cx"))?
-        - 1;
-    let cy = components[2]
-        .parse::<u16>()
-        .map_err(|_| anyhow!("Failed to parse Cy"))?
-        - 1;
-
-    Ok((cb, cx, cy))
+    let Some(c) = std::str::from_utf8(&data[..length])
+        .ok()
+        .and_then(|s| s.chars().next())
+    else {
+        return Parsed::Skip(1);
+    };
+    let modifiers = if c.is_uppercase() {
+        KeyModifiers::SHIFT
+    } else {
+        KeyModifiers::empty()
+    };
+    key(KeyCode::Char(c), modifiers, length)
 }
 
-fn convert_data_to_mouse_event(data: &[u8]) -> Option<crossterm::event::MouseEvent> {
-    let (cb, column, row) = decode_sgr_mouse_input(data.to_vec()).ok()?;
-    let kind = match cb {
-        0 => crossterm::event::MouseEventKind::Down(crossterm::event::MouseButton::Left),
-        1 => crossterm::event::MouseEventKind::Down(crossterm::event::MouseButton::Middle),
-        2 => crossterm::event::MouseEventKind::Down(crossterm::event::MouseButton::Right),
-        3 => crossterm::event::MouseEventKind::Up(crossterm::event::MouseButton::Left),
-        32 => crossterm::event::MouseEventKind::Drag(crossterm::event::MouseButton::Left),
-        33 => crossterm::event::MouseEventKind::Drag(crossterm::event::MouseButton::Middle),
-        34 => crossterm::event::MouseEventKind::Drag(crossterm::event::MouseButton::Right),
-        35 => crossterm::event::MouseEventKind::Moved,
-        64 => crossterm::event::MouseEventKind::ScrollUp,
-        65 => crossterm::event::MouseEventKind::ScrollDown,
-        96..=255 => {
-            return None;
-        }
-        _ => return None,
+// Sequences starting with `ESC [`.
+fn parse_csi(data: &[u8]) -> Parsed {
+    let Some(end) = data[2..]
+        .iter()
+        .position(|b| (0x40..=0x7e).contains(b))
+        .map(|position| position + 2)
+    else {
+        return Parsed::Incomplete;
     };
+    let length = end + 1;
 
-    let event = crossterm::event::MouseEvent {
-        kind,
-        column,
-        row,
-        modifiers: KeyModifiers::empty(),
+    // Mouse reports, `ESC [ < Cb ; Cx ; Cy M`, are not used.
+    if data[2] == b'<' {
+        return Parsed::Skip(length);
+    }
+
+    let params = std::str::from_utf8(&data[2..end])
+        .unwrap_or_default()
+        .split(';')
+        .filter_map(|param| param.parse::<u16>().ok())
+        .collect::<Vec<u16>>();
+    let modifiers = params
+        .get(1)
+        .map(|&param| decode_modifiers(param))
+        .unwrap_or(KeyModifiers::empty());
+
+    let code = match data[end] {
+        b'A' => KeyCode::Up,
+        b'B' => KeyCode::Down,
+        b'C' => KeyCode::Right,
+        b'D' => KeyCode::Left,
+        b'H' => KeyCode::Home,
+        b'F' => KeyCode::End,
+        b'Z' => return key(KeyCode::BackTab, KeyModifiers::SHIFT, length),
+        c @ b'P'..=b'S' => KeyCode::F(c - b'P' + 1),
+        b'~' => match params.first() {
+            Some(1 | 7) => KeyCode::Home,
+            Some(2) => KeyCode::Insert,
+            Some(3) => KeyCode::Delete,
+            Some(4 | 8) => KeyCode::End,
+            Some(5) => KeyCode::PageUp,
+            Some(6) => KeyCode::PageDown,
+            Some(&n @ 11..=15) => KeyCode::F(n as u8 - 10),
+            Some(&n @ 17..=21) => KeyCode::F(n as u8 - 11),
+            Some(&n @ 23..=24) => KeyCode::F(n as u8 - 12),
+            _ => return Parsed::Skip(length),
+        },
+        _ => return Parsed::Skip(length),
     };
 
-    Some(event)
+    key(code, modifiers, length)
+}
+
+// Sequences starting with `ESC O`, sent in application cursor mode.
+fn parse_ss3(c: u8) -> Parsed {
+    let code = match c {
+        b'A' => KeyCode::Up,
+        b'B' => KeyCode::Down,
+        b'C' => KeyCode::Right,
+        b'D' => KeyCode::Left,
+        b'H' => KeyCode::Home,
+        b'F' => KeyCode::End,
+        b'P'..=b'S' => KeyCode::F(c - b'P' + 1),
+        _ => return Parsed::Skip(3),
+    };
+    key(code, KeyModifiers::empty(), 3)
 }
 
-pub fn convert_data_to_crossterm_event(data: &[u8]) -> Option<crossterm::event::Event> {
-    if let Some(size) = data.strip_prefix(&[SSHEventHandler::CMD_RESIZE]) {
-        let cols = size.first().copied().unwrap_or(0) as u16;
-        let rows = size.last().copied().unwrap_or(0) as u16;
-        return Some(crossterm::event::Event::Resize(cols, rows));
+fn parse_event(data: &[u8]) -> Parsed {
+    match data {
+        [] | [0x1b] | [0x1b, b'O'] => Parsed::Incomplete,
+        [0x1b, b'[', ..] => parse_csi(data),
+        [0x1b, b'O', c, ..] => parse_ss3(*c),
+        [0x1b, 0x1b, ..] => key(KeyCode::Esc, KeyModifiers::empty(), 1),
+        // Alt is sent as an Esc prefix.
+        [0x1b, ..] => match parse_char(&data[1..]) {
+            Parsed::Event(Event::Key(event), length) => {
+                key(event.code, event.modifiers | KeyModifiers::ALT, length + 1)
+            }
+            Parsed::Skip(length) => Parsed::Skip(length + 1),
+            parsed => parsed,
+        },
+        _ => parse_char(data),
     }
+}
 
-    if data.starts_with(&[27, 91, 60]) {
-        if let Some(event) = convert_data_to_mouse_event(data) {
-            return Some(crossterm::event::Event::Mouse(event));
+// Streaming parser of the bytes sent by the client terminal. A packet can hold
+// several keys, and sequences split across packets are kept until the rest arrives.
+#[derive(Debug, Default)]
+pub struct InputParser {
+    buffer: Vec<u8>,
+    // Text received so far, while inside a bracketed paste.
+    paste: Option<Vec<u8>>,
+    // When the unfinished sequence in the buffer started.
+    pending_since: Option<Instant>,
+}
+
+impl InputParser {
+    const PASTE_START: &'static [u8] = b"\x1b[200~";
+    const PASTE_END: &'static [u8] = b"\x1b[201~";
+    const MAX_PASTE_LENGTH: usize = 4096;
+    // Unfinished sequences longer than this are garbage.
+    const MAX_SEQUENCE_LENGTH: usize = 64;
+    // The rest of a sequence split across packets arrives well within this time.
+    const PENDING_TIMEOUT: Duration = Duration::from_millis(100);
+
+    pub fn parse(&mut self, data: &[u8]) -> Vec<Event> {
+        self.buffer.extend_from_slice(data);
+        let mut events = vec![];
+
+        loop {
+            if let Some(paste) = self.paste.as_mut() {
+                let Some(end) = self
+                    .buffer
+                    .windows(Self::PASTE_END.len())
+                    .position(|window| window == Self::PASTE_END)
+                else {
+                    // The end marker may be split across packets.
+                    let keep = self.buffer.len().min(Self::PASTE_END.len() - 1);
+                    paste.extend(self.buffer.drain(..self.buffer.len() - keep));
+                    paste.truncate(Self::MAX_PASTE_LENGTH);
+                    break;
+                };
+
+                paste.extend(self.buffer.drain(..end));
+                paste.truncate(Self::MAX_PASTE_LENGTH);
+                self.buffer.drain(..Self::PASTE_END.len());
+                events.push(Event::Paste(String::from_utf8_lossy(paste).into_owned()));
+                self.paste = None;
+                continue;
+            }
+
+            if self.buffer.starts_with(Self::PASTE_START) {
+                self.buffer.drain(..Self::PASTE_START.len());
+                self.paste = Some(vec![]);
+                continue;
+            }
+
+            match parse_event(&self.buffer) {
+                Parsed::Event(event, length) => {
+                    self.buffer.drain(..length);
+                    events.push(event);
+                }
+                Parsed::Skip(length) => {
+                    self.buffer.drain(..length);
+                }
+                Parsed::Incomplete => {
+                    if self.buffer.len() > Self::MAX_SEQUENCE_LENGTH {
+                        self.buffer.clear();
+                    }
+                    break;
+                }
+            }
         }
-    } else {
-        if let Some(event) = convert_data_to_key_event(data) {
-            return Some(crossterm::event::Event::Key(event));
+
+        if self.buffer.is_empty() || self.paste.is_some() {
+            self.pending_since = None;
+        } else {
+            self.pending_since.get_or_insert_with(Instant::now);
         }
+
+        events
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending_since.is_some()
     }
 
-    None
+    pub fn is_timed_out(&self) -> bool {
+        self.pending_since
+            .is_some_and(|instant| instant.elapsed() >= Self::PENDING_TIMEOUT)
+    }
+
+    // Ends the unfinished sequence. When nothing followed, a short Esc prefix was
+    // typed as is: Esc, or Alt with `[` or `O`. Anything else is dropped.
+    pub fn flush(&mut self) -> Vec<Event> {
+        self.pending_since = None;
+        let buffer = std::mem::take(&mut self.buffer);
+
+        let event = match buffer[..] {
+            [0x1b] => KeyEvent::new(KeyCode::Esc, KeyModifiers::empty()),
+            [0x1b, c] => KeyEvent::new(KeyCode::Char(c as char), KeyModifiers::ALT),
+            _ => return vec![],
+        };
+        vec![Event::Key(event)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InputParser;
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+
+    fn keys(events: Vec<Event>) -> Vec<(KeyCode, KeyModifiers)> {
+        events
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Key(KeyEvent {
+                    code, modifiers, ..
+                }) => Some((code, modifiers)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        let mut parser = InputParser::default();
+        let none = KeyModifiers::empty();
+
+        // Several keys in one packet, including multi-byte UTF-8.
+        assert_eq!(
+            keys(parser.parse("wé→\r".as_bytes())),
+            vec![
+                (KeyCode::Char('w'), none),
+                (KeyCode::Char('é'), none),
+                (KeyCode::Char('→'), none),
+                (KeyCode::Enter, none),
+            ]
+        );
+
+        // Normal and application cursor mode arrows, with modifiers.
+        assert_eq!(
+            keys(parser.parse(b"\x1b[A\x1bOB\x1b[1;5C\x1b[1;2D")),
+            vec![
+                (KeyCode::Up, none),
+                (KeyCode::Down, none),
+                (KeyCode::Right, KeyModifiers::CONTROL),
+                (KeyCode::Left, KeyModifiers::SHIFT),
+            ]
+        );
+
+        // Navigation and function keys, as sent by xterm.
+        assert_eq!(
+            keys(parser.parse(b"\x1b[H\x1b[4~\x1b[5~\x1b[6~\x1b[3~\x1bOP\x1b[15~\x1b[24;3~")),
+            vec![
+                (KeyCode::Home, none),
+                (KeyCode::End, none),
+                (KeyCode::PageUp, none),
+                (KeyCode::PageDown, none),
+                (KeyCode::Delete, none),
+                (KeyCode::F(1), none),
+                (KeyCode::F(5), none),
+                (KeyCode::F(12), KeyModifiers::ALT),
+            ]
+        );
+
+        // Control characters and Alt prefixes.
+        assert_eq!(
+            keys(parser.parse(b"\x01\x7f\x1bx\x1b\x1b[Z\x03")),
+            vec![
+                (KeyCode::Char('a'), KeyModifiers::CONTROL),
+                (KeyCode::Backspace, none),
+                (KeyCode::Char('x'), KeyModifiers::ALT),
+                (KeyCode::Esc, none),
+                (KeyCode::BackTab, KeyModifiers::SHIFT),
+                (KeyCode::Esc, none),
+            ]
+        );
+
+        // A lone Esc is the Esc key once nothing follows it.
+        assert!(parser.parse(b"\x1b").is_empty());
+        assert!(parser.is_pending());
+        assert_eq!(keys(parser.flush()), vec![(KeyCode::Esc, none)]);
+        assert!(!parser.is_pending());
+    }
+
+    #[test]
+    fn test_parse_split_sequences() {
+        let mut parser = InputParser::default();
+
+        // Packets can end right after Esc.
+        assert!(parser.parse(b"\x1b").is_empty());
+        assert_eq!(
+            keys(parser.parse(b"[A")),
+            vec![(KeyCode::Up, KeyModifiers::empty())]
+        );
+        assert!(!parser.is_pending());
+
+        assert!(parser.parse(b"\x1b[1;").is_empty());
+        assert_eq!(
+            keys(parser.parse(b"5A")),
+            vec![(KeyCode::Up, KeyModifiers::CONTROL)]
+        );
+
+        let bytes = "ü".as_bytes();
+        assert!(parser.parse(&bytes[..1]).is_empty());
+        assert_eq!(
+            keys(parser.parse(&bytes[1..])),
+            vec![(KeyCode::Char('ü'), KeyModifiers::empty())]
+        );
+
+        // Unknown sequences are dropped without losing the following keys.
+        assert_eq!(
+            keys(parser.parse(b"\x1b[99xq")),
+            vec![(KeyCode::Char('q'), KeyModifiers::empty())]
+        );
+    }
+
+    #[test]
+    fn test_parse_paste_and_mouse_reports() {
+        let mut parser = InputParser::default();
+
+        // Pasted text is not read as keys, even when the end marker is split.
+        assert!(parser
+            .parse("\x1b[200~héllo q\x1b[20".as_bytes())
+            .is_empty());
+        let events = parser.parse(b"1~w");
+        assert_eq!(events[0], Event::Paste("héllo q".to_string()));
+        assert_eq!(
+            keys(events),
+            vec![(KeyCode::Char('w'), KeyModifiers::empty())]
+        );
+
+        // Mouse reports are skipped, and Ctrl-D is a key like any other.
+        assert_eq!(
+            keys(parser.parse(b"\x1b[<0;10;5M\x1b[<0;10;5m\x04x(")),
+            vec![
+                (KeyCode::Char('d'), KeyModifiers::CONTROL),
+                (KeyCode::Char('x'), KeyModifiers::empty()),
+                (KeyCode::Char('('), KeyModifiers::empty()),
+            ]
+        );
+    }
 }
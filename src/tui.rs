@@ -1,10 +1,12 @@
 use crate::game::Game;
+use crate::game::{Chat, ChatChannel, Entity, GameInstance, HeroInteractions};
 use crate::ssh::SSHWriterProxy;
 use crate::ui;
+use crate::ui::ui::{SpectatorView, UiScreen};
 use crate::AppResult;
 use crate::PlayerId;
 use crossterm::cursor::{Hide, Show};
-use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use crossterm::event::{DisableBracketedPaste, EnableBracketedPaste};
 use crossterm::terminal::Clear;
 use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
 use ratatui::layout::Rect;
@@ -19,17 +21,37 @@ use tokio_util::sync::CancellationToken;
 pub struct Tui {
     pub id: PlayerId,
     username: String,
+    is_registered: bool,
+    screen: UiScreen,
+    // Spectators have no hero.
+    spectator_view: Option<SpectatorView>,
+    // The game the client is in, none while in the lobby.
+    instance: Option<GameInstance>,
+    invite_code: String,
+    lobby_message: Option<String>,
+    // Position in the queue of a full server, starting from 1.
+    queue_position: Option<usize>,
+    // Interactions of the private games created from the lobby.
+    interactions: HeroInteractions,
+    // Message being typed, none when not chatting.
+    chat_draft: Option<String>,
+    chat_channel: ChatChannel,
+    chat_notice: Option<String>,
+    // Server announcement shown on every screen, such as an upcoming restart.
+    banner: Option<String>,
     start_instant: Instant,
     terminal: Terminal<CrosstermBackend<SSHWriterProxy>>,
     client_shutdown: CancellationToken,
 }
 
 impl Tui {
+    pub const INVITE_CODE_LENGTH: usize = 6;
+
     fn init(&mut self) -> AppResult<()> {
         crossterm::execute!(
             self.terminal.backend_mut(),
             EnterAlternateScreen,
-            EnableMouseCapture,
+            EnableBracketedPaste,
             Clear(crossterm::terminal::ClearType::All),
             Hide
         )?;
@@ -41,9 +63,194 @@ impl Tui {
         self.username.as_str()
     }
 
+    pub fn is_registered(&self) -> bool {
+        self.is_registered
+    }
+
+    pub fn instance(&self) -> Option<&GameInstance> {
+        self.instance.as_ref()
+    }
+
+    pub fn join(&mut self, instance: GameInstance) {
+        self.instance = Some(instance);
+        self.invite_code.clear();
+        self.lobby_message = None;
+        self.queue_position = None;
+    }
+
+    pub fn invite_code(&self) -> &str {
+        &self.invite_code
+    }
+
+    pub fn push_invite_code_digit(&mut self, digit: char) {
+        if self.invite_code.len() < Self::INVITE_CODE_LENGTH {
+            self.invite_code.push(digit);
+        }
+    }
+
+    pub fn pop_invite_code_digit(&mut self) {
+        self.invite_code.pop();
+    }
+
+    pub fn interactions(&self) -> HeroInteractions {
+        self.interactions
+    }
+
+    pub fn toggle_interaction(&mut self, key: char) {
+        match key {
+            'b' => self.interactions.blocking = !self.interactions.blocking,
+            's' => self.interactions.shoving = !self.interactions.shoving,
+            'g' => self.interactions.trading = !self.interactions.trading,
+            _ => {}
+        }
+    }
+
+    pub fn set_lobby_message(&mut self, message: String) {
+        self.lobby_message = Some(message);
+    }
+
+    pub fn set_banner(&mut self, banner: String) {
+        self.banner = Some(banner);
+    }
+
+    pub fn is_queued(&self) -> bool {
+        self.queue_position.is_some()
+    }
+
+    pub fn set_queue_position(&mut self, queue_position: Option<usize>) {
+        self.queue_position = queue_position;
+    }
+
+    pub fn draw_lobby(&mut self, public_players: usize, private_players: usize) -> AppResult<()> {
+        let mut rendered = Ok(());
+        self.terminal.draw(|frame| {
+            rendered = if let Some(queue_position) = self.queue_position {
+                ui::ui::render_queue(frame, &self.username, queue_position, self.start_instant)
+            } else {
+                ui::ui::render_lobby(
+                    frame,
+                    &self.username,
+                    (public_players, private_players),
+                    &self.invite_code,
+                    self.lobby_message.as_deref(),
+                    // Spectators can only watch existing labyrinths.
+                    self.spectator_view.is_none().then_some(self.interactions),
+                    self.start_instant,
+                )
+            };
+            if let Some(banner) = self.banner.as_ref() {
+                ui::ui::render_banner(frame, banner);
+            }
+        })?;
+        rendered
+    }
+
+    pub fn set_spectator(&mut self) {
+        self.spectator_view = Some(SpectatorView::Room(0));
+    }
+
+    pub fn is_spectator(&self) -> bool {
+        self.spectator_view.is_some()
+    }
+
+    pub fn toggle_spectator_view(&mut self, game: &Game) {
+        self.spectator_view = match self.spectator_view {
+            Some(SpectatorView::Hero(hero_id)) => {
+                let maze_id = game.get_hero(&hero_id).map(|hero| hero.maze_id());
+                Some(SpectatorView::Room(maze_id.unwrap_or_default()))
+            }
+            Some(SpectatorView::Room(maze_id)) => Some(
+                game.heros_in_maze(maze_id)
+                    .first()
+                    .map(|hero| hero.id())
+                    .or_else(|| game.hero_ids().first().copied())
+                    .map(SpectatorView::Hero)
+                    .unwrap_or(SpectatorView::Room(maze_id)),
+            ),
+            None => None,
+        };
+    }
+
+    pub fn cycle_spectator_view(&mut self, game: &Game, forward: bool) {
+        self.spectator_view = match self.spectator_view {
+            Some(SpectatorView::Hero(hero_id)) => {
+                let hero_ids = game.hero_ids();
+                if hero_ids.is_empty() {
+                    Some(SpectatorView::Room(0))
+                } else {
+                    let index = hero_ids.iter().position(|id| *id == hero_id);
+                    let next = match (index, forward) {
+                        (Some(index), true) => (index + 1) % hero_ids.len(),
+                        (Some(index), false) => (index + hero_ids.len() - 1) % hero_ids.len(),
+                        (None, _) => 0,
+                    };
+                    Some(SpectatorView::Hero(hero_ids[next]))
+                }
+            }
+            Some(SpectatorView::Room(maze_id)) => Some(SpectatorView::Room(if forward {
+                (maze_id + 1) % game.number_of_rooms()
+            } else {
+                (maze_id + game.number_of_rooms() - 1) % game.number_of_rooms()
+            })),
+            None => None,
+        };
+    }
+
+    pub fn is_chatting(&self) -> bool {
+        self.chat_draft.is_some()
+    }
+
+    pub fn start_chat(&mut self) {
+        self.chat_draft = Some(String::new());
+        self.chat_notice = None;
+    }
+
+    pub fn cancel_chat(&mut self) {
+        self.chat_draft = None;
+    }
+
+    pub fn push_chat_char(&mut self, c: char) {
+        if let Some(draft) = self.chat_draft.as_mut() {
+            if draft.chars().count() < Chat::MAX_MESSAGE_LENGTH {
+                draft.push(c);
+            }
+        }
+    }
+
+    pub fn pop_chat_char(&mut self) {
+        if let Some(draft) = self.chat_draft.as_mut() {
+            draft.pop();
+        }
+    }
+
+    pub fn cycle_chat_channel(&mut self) {
+        self.chat_channel = self.chat_channel.next();
+    }
+
+    // Ends the chat entry, returning the message to send.
+    pub fn take_chat_message(&mut self) -> Option<(ChatChannel, String)> {
+        self.chat_draft
+            .take()
+            .map(|draft| (self.chat_channel, draft))
+    }
+
+    // Errors while sending keep the draft open to be edited.
+    pub fn set_chat_notice(&mut self, draft: String, notice: String) {
+        self.chat_draft = Some(draft);
+        self.chat_notice = Some(notice);
+    }
+
+    pub fn toggle_stats_screen(&mut self) {
+        self.screen = match self.screen {
+            UiScreen::Stats => UiScreen::Game,
+            _ => UiScreen::Stats,
+        };
+    }
+
     pub fn new(
         id: PlayerId,
         username: String,
+        is_registered: bool,
         width: u16,
         height: u16,
         writer: SSHWriterProxy,
@@ -63,6 +270,18 @@ impl Tui {
         let mut tui = Self {
             id,
             username,
+            is_registered,
+            screen: UiScreen::default(),
+            spectator_view: None,
+            instance: None,
+            invite_code: String::new(),
+            lobby_message: None,
+            queue_position: None,
+            interactions: HeroInteractions::default(),
+            chat_draft: None,
+            chat_channel: ChatChannel::default(),
+            chat_notice: None,
+            banner: None,
             start_instant: Instant::now(),
             terminal,
             client_shutdown,
@@ -74,11 +293,40 @@ impl Tui {
     }
 
     pub fn draw(&mut self, game: &Game) -> AppResult<()> {
+        // Follow someone else when the watched hero leaves.
+        if let Some(SpectatorView::Hero(hero_id)) = self.spectator_view {
+            if game.get_hero(&hero_id).is_none() {
+                self.cycle_spectator_view(game, true);
+            }
+        }
+
+        // Rendering errors are returned after the frame is drawn, so that other widgets still show.
+        let mut rendered = Ok(());
+        if let Some(view) = self.spectator_view {
+            self.terminal.draw(|frame| {
+                rendered = ui::ui::render_spectator(frame, game, view, self.start_instant);
+                if let Some(banner) = self.banner.as_ref() {
+                    ui::ui::render_banner(frame, banner);
+                }
+            })?;
+            return rendered;
+        }
+
         self.terminal.draw(|frame| {
-            ui::ui::render(frame, game, self.id, self.start_instant)
-                .expect("Error while rendering game.")
+            rendered = ui::ui::render(frame, game, self.id, self.screen, self.start_instant);
+            if let Some(draft) = self.chat_draft.as_ref() {
+                ui::ui::render_chat_input(
+                    frame,
+                    self.chat_channel,
+                    draft,
+                    self.chat_notice.as_deref(),
+                );
+            }
+            if let Some(banner) = self.banner.as_ref() {
+                ui::ui::render_banner(frame, banner);
+            }
         })?;
-        Ok(())
+        rendered
     }
 
     pub async fn push_data(&mut self) -> AppResult<()> {
@@ -102,7 +350,7 @@ impl Tui {
         crossterm::execute!(
             self.terminal.backend_mut(),
             LeaveAlternateScreen,
-            DisableMouseCapture,
+            DisableBracketedPaste,
             Clear(crossterm::terminal::ClearType::All),
             Show
         )?;
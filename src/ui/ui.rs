@@ -1,7 +1,10 @@
 use super::utils::{img_to_lines, RataColor};
 use crate::{
     constants::UI_SCREEN_SIZE,
-    game::{Entity, Game, GameColors, Hero, Maze, MAX_MAZE_ID},
+    game::{
+        Achievement, ChatChannel, Direction, Entity, Game, GameColors, GameInstance, Hero,
+        HeroInteractions, KeyColor, Maze, PowerUp, Race, RaceResult, Trap,
+    },
     AppResult, PlayerId,
 };
 use anyhow::anyhow;
@@ -14,7 +17,27 @@ use ratatui::{
     widgets::{Block, BorderType, Paragraph, Wrap},
     Frame,
 };
-use std::time::{Duration, Instant};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use strum::IntoEnumIterator;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum UiScreen {
+    #[default]
+    Game,
+    Stats,
+    // Full view of the hero room, used when watching replays.
+    GodView,
+}
+
+// What a spectator is watching: a hero from its point of view, or a whole room.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpectatorView {
+    Hero(PlayerId),
+    Room(usize),
+}
 
 const MINORADAR: [&'static str; 8] = ["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
 const NAME_LENGTH: usize = 13;
@@ -102,13 +125,50 @@ fn render_header(frame: &mut Frame, game: &Game, hero: &Hero, area: Rect) -> App
     let number_of_players = game.number_of_players();
     let maze = game.get_maze(hero.maze_id());
 
+    let mut line = vec![Span::raw(format!(
+        "There {} {} hero{} in the {}labyrinth...",
+        if number_of_players == 1 { "is" } else { "are" },
+        number_of_players,
+        if number_of_players == 1 { "" } else { "es" },
+        match game.instance() {
+            GameInstance::Public => "".to_string(),
+            GameInstance::Private { invite_code } =>
+                format!("private (invite code {}) ", invite_code),
+        }
+    ))];
+
+    let interactions = game.interactions();
+    if !interactions.names().is_empty() {
+        let mut keys = vec![];
+        if interactions.shoving {
+            keys.push("p: shove");
+        }
+        if interactions.trading {
+            keys.push("g: give power up");
+        }
+        line.push(Span::styled(
+            format!(
+                "  Interactions: {}{}",
+                interactions.names().join(", "),
+                if keys.is_empty() {
+                    "".to_string()
+                } else {
+                    format!(" ({})", keys.join("  "))
+                }
+            ),
+            Color::LightMagenta,
+        ));
+    }
+
+    if let Some(race) = game.race() {
+        line.push(Span::styled(
+            format!("  {}", race_status(race)),
+            Color::Yellow,
+        ));
+    }
+
     let mut lines = vec![
-        Line::from(format!(
-            "There {} {} hero{} in the labyrinth...",
-            if number_of_players == 1 { "is" } else { "are" },
-            number_of_players,
-            if number_of_players == 1 { "" } else { "es" },
-        )),
+        Line::from(line),
         Line::from(vec![
             Span::styled(format!("{}  ", hero.name()), GameColors::HERO.to_color()),
             Span::raw(format!(
@@ -146,14 +206,39 @@ fn render_header(frame: &mut Frame, game: &Game, hero: &Hero, area: Rect) -> App
     }
     lines.push(Line::from(line));
 
-    lines.push(Line::from(Span::raw(format!(
+    let mut line = vec![Span::raw(format!(
         "Power up {}collected",
         if let Some(power_up) = hero.power_up_collected_in_maze() {
             format!("({}) ", power_up)
         } else {
             "not ".to_string()
         }
-    ))));
+    ))];
+
+    if let Some(gap) = game.ghost_gap(&hero.id()) {
+        let (text, color) = if gap > 0.0 {
+            (format!("{:.1}s behind", gap), Color::Red)
+        } else {
+            (format!("{:.1}s ahead", -gap), Color::LightGreen)
+        };
+        line.push(Span::raw("  Ghost "));
+        line.push(Span::styled(text, color));
+    }
+
+    if let Some((depth, best_depth)) = game.team_depth() {
+        let room = |depth: usize| {
+            if depth >= game.number_of_rooms() {
+                "exit".to_string()
+            } else {
+                format!("room {}", depth + 1)
+            }
+        };
+        line.push(Span::styled(
+            format!("  Team depth {} (best {})", room(depth), room(best_depth)),
+            GameColors::OTHER_HERO.to_color(),
+        ));
+    }
+    lines.push(Line::from(line));
 
     frame.render_widget(
         Paragraph::new(lines).block(Block::bordered().border_type(BorderType::Double)),
@@ -163,55 +248,263 @@ fn render_header(frame: &mut Frame, game: &Game, hero: &Hero, area: Rect) -> App
     Ok(())
 }
 
+fn race_status(race: &Race) -> String {
+    match race {
+        Race::Gathering => "Race: press Enter to start".to_string(),
+        Race::Countdown { instant } => format!(
+            "Race starts in {}s",
+            Race::COUNTDOWN_DURATION
+                .saturating_sub(instant.elapsed())
+                .as_secs()
+                + 1
+        ),
+        Race::Running { instant } => format!(
+            "Race: {} left",
+            format_duration(&Race::TIME_LIMIT.saturating_sub(instant.elapsed()))
+        ),
+        Race::Finished { .. } => "Race over - Enter: new race".to_string(),
+    }
+}
+
+fn render_race_results(frame: &mut Frame, results: &[RaceResult], area: Rect) {
+    let mut lines = vec![Line::from("")];
+
+    for (index, result) in results.iter().enumerate() {
+        let outcome = if result.has_exited {
+            format!("exited in {}", format_duration(&result.duration))
+        } else {
+            format!(
+                "reached room {} in {}",
+                result.maze_id + 1,
+                format_duration(&result.duration)
+            )
+        };
+        lines.push(Line::from(vec![
+            Span::raw(format!("{:>3}. ", index + 1)),
+            Span::styled(
+                format!("{:<width$} ", result.name, width = NAME_LENGTH),
+                GameColors::HERO.to_color(),
+            ),
+            Span::raw(outcome),
+        ]));
+    }
+
+    if results.is_empty() {
+        lines.push(Line::from("Nobody raced."));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from("Press Enter to start a new race."));
+
+    frame.render_widget(
+        Paragraph::new(lines).block(
+            Block::bordered()
+                .title("Race results")
+                .border_type(BorderType::Double),
+        ),
+        area,
+    );
+}
+
+fn render_stats(frame: &mut Frame, game: &Game, hero: &Hero, area: Rect) -> AppResult<()> {
+    // Stats are created with the first event of the hero.
+    let Some(stats) = game.hero_stats(&hero.id()) else {
+        return Ok(());
+    };
+
+    let mut lines = vec![
+        Line::from(format!("Runs started     {}", stats.runs_started)),
+        Line::from(format!("Deaths           {}", stats.total_deaths())),
+        Line::from(format!(
+            "Nemesis          {}",
+            if let Some((name, kills)) = stats.top_killer() {
+                format!("{} ({})", name, kills)
+            } else {
+                "none".to_string()
+            }
+        )),
+        Line::from(format!(
+            "Power ups        {}",
+            PowerUp::iter()
+                .map(|power_up| format!(
+                    "{} {}",
+                    power_up,
+                    stats
+                        .power_ups_collected
+                        .get(&power_up)
+                        .copied()
+                        .unwrap_or_default()
+                ))
+                .join("  ")
+        )),
+        Line::from(format!("Distance walked  {}", stats.distance_walked)),
+        Line::from(format!(
+            "Play time        {}",
+            format_duration(&stats.play_time)
+        )),
+        Line::from(format!(
+            "Achievements     {}/{} {}",
+            stats.achievements.len(),
+            Achievement::iter().count(),
+            Achievement::iter()
+                .filter(|achievement| stats.achievements.contains(achievement))
+                .join(", ")
+        )),
+        Line::from(""),
+        Line::from("Room  Deaths  Fastest clear"),
+    ];
+
+    for maze_id in 0..game.number_of_rooms() {
+        lines.push(Line::from(format!(
+            "{:>4}  {:>6}  {}",
+            maze_id + 1,
+            stats
+                .deaths_per_room
+                .get(&maze_id)
+                .copied()
+                .unwrap_or_default(),
+            if let Some(duration) = stats.fastest_clears.get(&maze_id) {
+                format_duration(duration)
+            } else {
+                "-".to_string()
+            }
+        )));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from("Press 's' to go back to the labyrinth."));
+
+    frame.render_widget(
+        Paragraph::new(lines).block(
+            Block::bordered()
+                .title("Lifetime stats")
+                .border_type(BorderType::Double),
+        ),
+        area,
+    );
+
+    Ok(())
+}
+
 fn render_sidebar(frame: &mut Frame, game: &Game, hero: &Hero, area: Rect) -> AppResult<()> {
-    let split = Layout::vertical([
-        Constraint::Min(15),
-        Constraint::Max(12),
-        Constraint::Max(12),
-    ])
-    .split(area);
+    let mut keys_line = vec![Span::raw("Keys: ")];
+    if hero.held_keys_in_maze().is_empty() {
+        keys_line.push(Span::raw("none"));
+    }
+    for color in hero.held_keys_in_maze().iter() {
+        keys_line.push(Span::styled(
+            format!("{} ", KeyColor::glyph()),
+            color.rgba().to_color(),
+        ));
+    }
+
+    // Two legend entries per line, the second one always starting at the same column.
+    let legend_line = |entries: Vec<(Span<'static>, &str)>| {
+        Line::from(
+            entries
+                .into_iter()
+                .flat_map(|(glyph, label)| {
+                    let width = 11 - glyph.width();
+                    [glyph, Span::raw(format!(" {:width$}", label))]
+                })
+                .collect_vec(),
+        )
+    };
+    let glyph = |c: char| Span::raw(c.to_string());
+    let color = |color: image::Rgba<u8>| Span::styled("██", color.to_color());
 
     let lines = vec![
-        Line::from("←↑→↓: move"),
-        Line::from("'a'/'d': rotate"),
-        Line::from("'q'/Esc: quit"),
-        Line::from(""),
-        Line::from(vec![
-            Span::styled("██", GameColors::HERO.to_color()),
-            Span::raw(format!(" {:12}", "Hero")),
+        Line::from("←↑→↓: move  a/d: turn"),
+        Line::from("f: flare  s: stats"),
+        Line::from("t: chat  q/Esc: quit"),
+        Line::from("m/x: mark  1/2: ping"),
+        Line::from(format!(
+            "c: share marks ({})",
+            if hero.share_marks { "on" } else { "off" }
+        )),
+        legend_line(vec![
+            (color(GameColors::HERO), "hero"),
+            (color(GameColors::OTHER_HERO), "others"),
         ]),
-        Line::from(vec![
-            Span::styled("██", GameColors::OTHER_HERO.to_color()),
-            Span::raw(format!(" {:12}", "Other heros")),
+        legend_line(vec![
+            (color(GameColors::MINOTAUR), "minotaur"),
+            (color(GameColors::CHASING_MINOTAUR), "chasing"),
         ]),
-        Line::from(vec![
-            Span::styled("██", GameColors::MINOTAUR.to_color()),
-            Span::raw(format!(" {:12}", "Minotaur")),
+        legend_line(vec![
+            (color(GameColors::POWER_UP), "power up"),
+            (glyph(Trap::SpikePit.glyph()), "spikes"),
         ]),
-        Line::from(vec![
-            Span::styled("██", GameColors::CHASING_MINOTAUR.to_color()),
-            Span::raw(format!(" {:12}", "Minotaur (run!)")),
+        legend_line(vec![
+            (glyph(Trap::SlowTile.glyph()), "mud"),
+            (glyph(Trap::AlarmPlate.glyph()), "alarm"),
         ]),
-        Line::from(vec![
-            Span::styled("██", GameColors::POWER_UP.to_color()),
-            Span::raw(format!(" {:12}", "Power up")),
+        legend_line(vec![
+            (glyph(Trap::CollapsingFloor.glyph()), "crumbling"),
+            (glyph(Maze::teleporter_glyph()), "teleport"),
         ]),
-        Line::from(""),
-        Line::from(format!("Run from the minotaurs")),
-        Line::from(format!("and try to get as far")),
-        Line::from(format!("as possible.")),
+        legend_line(vec![
+            (glyph(Hero::MARK_GLYPH), "your mark"),
+            (glyph(Hero::SHARED_MARK_GLYPH), "shared"),
+        ]),
+        legend_line(vec![(glyph(Maze::gate_glyph(Direction::East)), "one-way")]),
+        Line::from(keys_line),
+        Line::from(format!(
+            "Flares {}  Marks {}/{}",
+            hero.flares(),
+            hero.marks_in_maze().len(),
+            Hero::MAX_MARKS_PER_MAZE
+        )),
     ];
 
+    // The help is always shown whole. Leaderboards keep at least three entries each,
+    // and grow to ten once the chat has room for a few messages.
+    let help_height = lines.len() as u16 + 2;
+    let leaderboard_height = (area.height.saturating_sub(help_height + 8) / 2).clamp(5, 12);
+    let split = Layout::vertical([
+        Constraint::Length(help_height),
+        Constraint::Min(0),
+        Constraint::Length(leaderboard_height),
+        Constraint::Length(leaderboard_height),
+    ])
+    .split(area);
+
     frame.render_widget(
         Paragraph::new(lines).block(Block::bordered().border_set(border::DOUBLE)),
         split[0],
     );
+
+    // Messages are wrapped by hand to show the most recent ones at the bottom.
+    let width = split[1].width.saturating_sub(2).max(1) as usize;
+    let height = split[1].height.saturating_sub(2) as usize;
+    let lines = game
+        .chat_messages(&hero.id())
+        .iter()
+        .flat_map(|message| {
+            let color = if message.channel == ChatChannel::Global {
+                Color::LightBlue
+            } else {
+                Color::White
+            };
+            format!("{}: {}", message.sender, message.text)
+                .chars()
+                .collect_vec()
+                .chunks(width)
+                .map(|chunk| Line::from(Span::styled(chunk.iter().collect::<String>(), color)))
+                .collect_vec()
+        })
+        .collect_vec();
+    let lines = lines[lines.len().saturating_sub(height)..].to_vec();
+
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Chat").border_set(border::DOUBLE)),
+        split[1],
+    );
     let lines = game
         .top_heros()
         .iter()
         .take(10)
         .map(|(id, name, maze_id, duration)| {
-            let record = if *maze_id < MAX_MAZE_ID {
+            let record = if *maze_id < game.number_of_rooms() {
                 format!("r{}", maze_id + 1,)
             } else {
                 format_duration(duration)
@@ -237,7 +530,7 @@ fn render_sidebar(frame: &mut Frame, game: &Game, hero: &Hero, area: Rect) -> Ap
                 .title("Top Heros")
                 .border_set(border::DOUBLE),
         ),
-        split[1],
+        split[2],
     );
 
     let lines = game
@@ -260,21 +553,17 @@ fn render_sidebar(frame: &mut Frame, game: &Game, hero: &Hero, area: Rect) -> Ap
                 .title("Top Minotaurs")
                 .border_set(border::DOUBLE),
         ),
-        split[2],
+        split[3],
     );
 
     Ok(())
 }
 
-pub fn render(
-    frame: &mut Frame,
-    game: &Game,
-    player_id: PlayerId,
-    start_instant: Instant,
-) -> AppResult<()> {
+// Returns true if the title or a frame size warning was rendered instead of the game.
+fn render_title_or_size_warning(frame: &mut Frame, start_instant: Instant) -> bool {
     if start_instant.elapsed() < Duration::from_millis(1500) {
         frame.render_widget(title_paragraph(), frame.area().inner(Margin::new(4, 2)));
-        return Ok(());
+        return true;
     }
 
     if frame.area().width < UI_SCREEN_SIZE.0 || frame.area().height < UI_SCREEN_SIZE.1 {
@@ -290,6 +579,303 @@ pub fn render(
             .wrap(Wrap { trim: true }),
             frame.area(),
         );
+        return true;
+    }
+
+    false
+}
+
+// Status line drawn over the bottom row of the frame.
+pub fn render_chat_input(
+    frame: &mut Frame,
+    channel: ChatChannel,
+    draft: &str,
+    notice: Option<&str>,
+) {
+    let status = if let Some(notice) = notice {
+        format!(" {}  [{}] {}_", notice, channel, draft)
+    } else {
+        format!(
+            " [{}] {}_  Enter: send  Tab: channel  Esc: cancel",
+            channel, draft
+        )
+    };
+    render_status_line(frame, status);
+}
+
+// Server wide announcement on the top line, drawn over everything else.
+pub fn render_banner(frame: &mut Frame, banner: &str) {
+    let area = frame.area();
+    let banner_area = Rect::new(area.x, area.y, area.width, 1.min(area.height));
+    frame.render_widget(
+        Paragraph::new(banner)
+            .centered()
+            .set_style(Style::default().fg(Color::Black).bg(Color::Yellow)),
+        banner_area,
+    );
+}
+
+pub fn render_status_line(frame: &mut Frame, status: String) {
+    let area = frame.area();
+    let status_area = Rect::new(
+        area.x,
+        (area.y + area.height).saturating_sub(1),
+        area.width,
+        1,
+    );
+    frame.render_widget(
+        Paragraph::new(status).set_style(Style::default().fg(Color::Black).bg(Color::Gray)),
+        status_area,
+    );
+}
+
+fn render_room(frame: &mut Frame, game: &Game, maze_id: usize) {
+    let maze = game.get_maze(maze_id);
+    let heros = game.heros_in_maze(maze_id);
+    let num_minotaurs = game.minotaurs_in_maze(maze_id);
+
+    let v_split = Layout::vertical([Constraint::Length(6), Constraint::Min(1)]).split(frame.area());
+
+    let lines = vec![
+        Line::from(format!(
+            "There {} {} hero{} in the labyrinth...",
+            if game.number_of_players() == 1 {
+                "is"
+            } else {
+                "are"
+            },
+            game.number_of_players(),
+            if game.number_of_players() == 1 {
+                ""
+            } else {
+                "es"
+            },
+        )),
+        Line::from(if maze.success_rate().is_nan() {
+            format!("Room {} - Not attempted yet", maze_id + 1)
+        } else {
+            format!(
+                "Room {} - Pass rate {:.2}%",
+                maze_id + 1,
+                maze.success_rate() * 100.0
+            )
+        }),
+        Line::from(format!(
+            "{} minotaur{}",
+            num_minotaurs,
+            if num_minotaurs == 1 { "" } else { "s" }
+        )),
+        Line::from(vec![
+            Span::raw(format!("{} here: ", heros.len())),
+            Span::styled(
+                heros
+                    .iter()
+                    .map(|hero| {
+                        if game.is_detached(&hero.id()) {
+                            format!("{} (reconnecting)", hero.name())
+                        } else {
+                            hero.name().to_string()
+                        }
+                    })
+                    .join(", "),
+                GameColors::HERO.to_color(),
+            ),
+        ]),
+    ];
+
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().border_type(BorderType::Double)),
+        v_split[0],
+    );
+
+    frame.render_widget(
+        Paragraph::new(img_to_lines(
+            &game.draw_room(maze_id),
+            HashMap::new(),
+            Maze::background_color(),
+        ))
+        .block(Block::bordered().border_type(BorderType::Double)),
+        v_split[1],
+    );
+}
+
+pub fn render_lobby(
+    frame: &mut Frame,
+    username: &str,
+    // Heros in the public labyrinth and in private ones.
+    (public_players, private_players): (usize, usize),
+    invite_code: &str,
+    message: Option<&str>,
+    // Spectators cannot create labyrinths, so they have no interactions to choose.
+    interactions: Option<HeroInteractions>,
+    start_instant: Instant,
+) -> AppResult<()> {
+    if render_title_or_size_warning(frame, start_instant) {
+        return Ok(());
+    }
+
+    let mut lines = vec![
+        Line::from(format!("Welcome {}", username)),
+        Line::from(format!(
+            "There {} {} hero{} in the public labyrinth",
+            if public_players == 1 { "is" } else { "are" },
+            public_players,
+            if public_players == 1 { "" } else { "es" },
+        )),
+        Line::from(format!("and {} in private ones", private_players)),
+        Line::from(""),
+    ];
+
+    if let Some(interactions) = interactions {
+        lines.extend([
+            Line::from("Enter: join the public labyrinth"),
+            Line::from("n: create a private labyrinth"),
+            Line::from("r: create a private race"),
+            Line::from("t: create a private team labyrinth"),
+            Line::from("0-9 + Enter: join with an invite code"),
+            Line::from("b/s/g: toggle blocking/shoving/trading"),
+            Line::from("q/Esc: quit"),
+            Line::from(""),
+            Line::from(format!(
+                "New private games interactions: {}",
+                if interactions.names().is_empty() {
+                    "none".to_string()
+                } else {
+                    interactions.names().join(", ")
+                }
+            )),
+        ]);
+    } else {
+        lines.extend([
+            Line::from("Enter: watch the public labyrinth"),
+            Line::from("0-9 + Enter: watch with an invite code"),
+            Line::from("q/Esc: quit"),
+            Line::from(""),
+        ]);
+    }
+    lines.push(Line::from(vec![
+        Span::raw("Invite code: "),
+        Span::styled(format!("{}_", invite_code), GameColors::HERO.to_color()),
+    ]));
+
+    if let Some(message) = message {
+        lines.push(Line::from(Span::styled(message.to_string(), Color::Red)));
+    }
+
+    let width = 50;
+    let height = lines.len() as u16 + 2;
+    let area = frame.area();
+    let popup = Rect::new(
+        area.x + (area.width.saturating_sub(width)) / 2,
+        area.y + (area.height.saturating_sub(height)) / 2,
+        width,
+        height,
+    )
+    .intersection(area);
+
+    frame.render_widget(
+        Paragraph::new(lines).centered().block(
+            Block::bordered()
+                .title("Lobby")
+                .border_type(BorderType::Double),
+        ),
+        popup,
+    );
+
+    Ok(())
+}
+
+// Waiting screen of players who tried to join a full server.
+pub fn render_queue(
+    frame: &mut Frame,
+    username: &str,
+    queue_position: usize,
+    start_instant: Instant,
+) -> AppResult<()> {
+    if render_title_or_size_warning(frame, start_instant) {
+        return Ok(());
+    }
+
+    let lines = vec![
+        Line::from(format!("Welcome {}", username)),
+        Line::from("The labyrinth is full"),
+        Line::from(""),
+        Line::from(vec![
+            Span::raw("You are number "),
+            Span::styled(format!("{}", queue_position), GameColors::HERO.to_color()),
+            Span::raw(" in the queue"),
+        ]),
+        Line::from("You will join as soon as a hero leaves"),
+        Line::from(""),
+        Line::from("q/Esc: quit"),
+    ];
+
+    let width = 50;
+    let height = lines.len() as u16 + 2;
+    let area = frame.area();
+    let popup = Rect::new(
+        area.x + (area.width.saturating_sub(width)) / 2,
+        area.y + (area.height.saturating_sub(height)) / 2,
+        width,
+        height,
+    )
+    .intersection(area);
+
+    frame.render_widget(
+        Paragraph::new(lines).centered().block(
+            Block::bordered()
+                .title("Queue")
+                .border_type(BorderType::Double),
+        ),
+        popup,
+    );
+
+    Ok(())
+}
+
+pub fn render_spectator(
+    frame: &mut Frame,
+    game: &Game,
+    view: SpectatorView,
+    start_instant: Instant,
+) -> AppResult<()> {
+    if render_title_or_size_warning(frame, start_instant) {
+        return Ok(());
+    }
+
+    let status = match view {
+        SpectatorView::Hero(hero_id) => {
+            render(frame, game, hero_id, UiScreen::Game, start_instant)?;
+            let name = game
+                .get_hero(&hero_id)
+                .map(|hero| hero.name())
+                .unwrap_or_default();
+            format!(
+                " Watching {}  ←/→: cycle heroes  v: room view  q/Esc: quit ",
+                name
+            )
+        }
+        SpectatorView::Room(maze_id) => {
+            render_room(frame, game, maze_id);
+            format!(
+                " Watching room {}  ←/→: cycle rooms  v: hero view  q/Esc: quit ",
+                maze_id + 1
+            )
+        }
+    };
+    render_status_line(frame, status);
+
+    Ok(())
+}
+
+pub fn render(
+    frame: &mut Frame,
+    game: &Game,
+    player_id: PlayerId,
+    screen: UiScreen,
+    start_instant: Instant,
+) -> AppResult<()> {
+    if render_title_or_size_warning(frame, start_instant) {
         return Ok(());
     }
 
@@ -306,10 +892,23 @@ pub fn render(
     let v_split = Layout::vertical([Constraint::Length(6), Constraint::Min(1)]).split(h_split[0]);
     render_header(frame, game, hero, v_split[0])?;
 
-    let image = game.draw(player_id)?;
+    if screen == UiScreen::Stats {
+        return render_stats(frame, game, hero, v_split[1]);
+    }
 
-    // Override empty positions.
-    let override_positions = game.image_char_overrides(player_id, &image)?;
+    if let Some(Race::Finished { results }) = game.race() {
+        render_race_results(frame, results, v_split[1]);
+        return Ok(());
+    }
+
+    let (image, override_positions) = if screen == UiScreen::GodView {
+        (game.draw_room(hero.maze_id()), HashMap::new())
+    } else {
+        let image = game.draw(player_id)?;
+        // Override empty positions.
+        let override_positions = game.image_char_overrides(player_id, &image)?;
+        (image, override_positions)
+    };
 
     frame.render_widget(
         Paragraph::new(img_to_lines(
@@ -321,9 +920,9 @@ pub fn render(
         v_split[1],
     );
 
-    if hero.is_dead() {
+    if let Some(race @ Race::Countdown { .. }) = game.race() {
         let width = 32;
-        let height = 6;
+        let height = 5;
         let popup = Rect::new(
             v_split[1].x + (v_split[1].width.saturating_sub(width)) / 2,
             v_split[1].y + (v_split[1].height.saturating_sub(height)) / 2,
@@ -333,14 +932,42 @@ pub fn render(
 
         frame.render_widget(
             Paragraph::new(vec![
-                Line::from(format!("{}", hero.name())),
-                Line::from(format!("died while exploring room {}", hero.maze_id() + 1)),
+                Line::from("Get ready!"),
+                Line::from(race_status(race)),
             ])
             .centered()
-            .set_style(Style::default().fg(Color::Black).bg(Color::Red))
+            .set_style(Style::default().fg(Color::Black).bg(Color::Yellow))
             .block(Block::bordered().border_type(BorderType::QuadrantOutside)),
             popup,
         );
+    } else if hero.is_dead() {
+        let width = 32;
+        let height = 6;
+        let popup = Rect::new(
+            v_split[1].x + (v_split[1].width.saturating_sub(width)) / 2,
+            v_split[1].y + (v_split[1].height.saturating_sub(height)) / 2,
+            width,
+            height,
+        );
+
+        let mut lines = vec![
+            Line::from(format!("{}", hero.name())),
+            Line::from(format!("died while exploring room {}", hero.maze_id() + 1)),
+        ];
+        if let Some(window) = game.revive_window(&hero.id()) {
+            lines.push(Line::from(format!(
+                "Revive window {}s",
+                window.as_secs_f64().ceil() as u64
+            )));
+        }
+
+        frame.render_widget(
+            Paragraph::new(lines)
+                .centered()
+                .set_style(Style::default().fg(Color::Black).bg(Color::Red))
+                .block(Block::bordered().border_type(BorderType::QuadrantOutside)),
+            popup,
+        );
     } else if let Some(duration) = hero.has_won().as_ref() {
         let width = 32;
         let height = 6;
@@ -364,5 +991,47 @@ pub fn render(
         );
     }
 
+    let kill_feed = game.kill_feed();
+    if !kill_feed.is_empty() {
+        let height = kill_feed.len() as u16;
+        let feed = Rect::new(
+            v_split[1].x + 1,
+            (v_split[1].y + v_split[1].height).saturating_sub(height + 1),
+            v_split[1].width.saturating_sub(2),
+            height,
+        )
+        .intersection(v_split[1]);
+
+        frame.render_widget(
+            Paragraph::new(kill_feed.into_iter().map(Line::from).collect_vec())
+                .set_style(Style::default().fg(Color::Gray)),
+            feed,
+        );
+    }
+
+    // Achievement toasts are stacked at the top of the labyrinth.
+    for (index, achievement) in game.achievement_toasts(&player_id).iter().enumerate() {
+        let width = 40;
+        let height = 4;
+        let toast = Rect::new(
+            v_split[1].x + (v_split[1].width.saturating_sub(width)) / 2,
+            v_split[1].y + 1 + index as u16 * height,
+            width,
+            height,
+        )
+        .intersection(v_split[1]);
+
+        frame.render_widget(
+            Paragraph::new(vec![
+                Line::from(format!("Achievement unlocked: {}", achievement)),
+                Line::from(achievement.description()),
+            ])
+            .centered()
+            .set_style(Style::default().fg(Color::Black).bg(Color::Yellow))
+            .block(Block::bordered().border_type(BorderType::QuadrantOutside)),
+            toast,
+        );
+    }
+
     Ok(())
 }
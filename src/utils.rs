@@ -1,9 +1,22 @@
 use anyhow::anyhow;
-use std::path::PathBuf;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    io::Write,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 pub type AppResult<T> = Result<T, anyhow::Error>;
 pub type PlayerId = uuid::Uuid;
 
+// Seconds since the unix epoch.
+pub fn now_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
 pub fn store_path(filename: &str) -> AppResult<PathBuf> {
     let dirs = directories::ProjectDirs::from("org", "frittura", "minotaur")
         .ok_or(anyhow!("Failed to get directories"))?;
@@ -14,3 +27,64 @@ pub fn store_path(filename: &str) -> AppResult<PathBuf> {
     let path = config_dirs.join(filename);
     Ok(path)
 }
+
+pub fn load_json<T: DeserializeOwned>(filename: &str) -> AppResult<Option<T>> {
+    let path = store_path(filename)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    Ok(Some(serde_json::from_slice(&std::fs::read(path)?)?))
+}
+
+// Moves an unreadable file out of the way, so that it can be recovered by hand.
+// Backups are numbered, so that an earlier one is never overwritten.
+pub fn backup_json(filename: &str) -> AppResult<PathBuf> {
+    let path = store_path(filename)?;
+    let backup_path = (1..)
+        .map(|number| path.with_extension(format!("json.{}.bak", number)))
+        .find(|backup_path| !backup_path.exists())
+        .ok_or(anyhow!("No backup name left for {}", filename))?;
+    std::fs::rename(&path, &backup_path)?;
+    Ok(backup_path)
+}
+
+// A store that cannot be loaded is backed up and started anew. If the backup
+// fails, the store is left out of saving, so that it is not overwritten.
+// Returns whether the store can be saved.
+pub fn load_store<T: Default>(
+    name: &str,
+    load: impl FnOnce() -> AppResult<T>,
+    backup: impl FnOnce() -> AppResult<PathBuf>,
+) -> (T, bool) {
+    match load() {
+        Ok(store) => (store, true),
+        Err(e) => {
+            log::error!("Could not load {}: {}", name, e);
+            match backup() {
+                Ok(path) => {
+                    log::warn!("Backed up {} to {}", name, path.display());
+                    (T::default(), true)
+                }
+                Err(e) => {
+                    log::error!("Could not back up {}: {}", name, e);
+                    log::warn!("Saving {} is disabled", name);
+                    (T::default(), false)
+                }
+            }
+        }
+    }
+}
+
+// Writes to a temporary file first, so that a crash never leaves a truncated file.
+pub fn save_json<T: Serialize>(filename: &str, value: &T) -> AppResult<()> {
+    let path = store_path(filename)?;
+    let tmp_path = path.with_extension("tmp");
+
+    let mut file = std::fs::File::create(&tmp_path)?;
+    file.write_all(&serde_json::to_vec(value)?)?;
+    file.sync_all()?;
+    std::fs::rename(tmp_path, path)?;
+
+    Ok(())
+}